@@ -1,21 +1,54 @@
-use crate::rendering::vertex::{self, VertexFormat};
-use crate::curves::Curves;
+use fractals::vertex::{self, POINT_SIZE, Vertex, VertexFormat};
+use fractals::{curves::Curve, escape_time::EscapeTime};
 
+/// `weld`: tolerance for merging nearly coincident vertices, see [`vertex::weld`]
 #[allow(clippy::needless_pass_by_value)]
-pub fn run(iteration: usize, curve: Curves) {
-    super::log_init("debug");
-    let mut curve_instance = curve.new_instance();
+pub fn run(iteration: usize, mut curve_instance: Box<dyn Curve>, weld: Option<f64>) {
     let vertex_format = curve_instance.vertex_format();
+    let stroke = curve_instance.stroke();
+
+    // actual limit depends on the device, but this is what every device supports
+    if curve_instance.instanced_estimate(iteration - 1).max_buffer_bytes() as u64 > wgpu::Limits::default().max_buffer_size {
+        log::warn!("buffers might be too large for some devices and have to be split there");
+    }
 
     log::info!("starting benchmark");
     let now = std::time::Instant::now();
 
+    // like in the window, only the base mesh is indexed
+    if let Some(instancing) = curve_instance.instancing() {
+        let instanced = instancing.instanced(iteration - 1);
+        index(&instanced.base, weld);
+        log::info!("completed benchmark in {:?} ({} instances)", now.elapsed(), instanced.instances.len());
+        return;
+    }
+
     let unformatted_vertices = curve_instance.vertices(iteration - 1);
     let raw_vertices = match vertex_format {
-        VertexFormat::Lines => &vertex::lines_as_triangles(unformatted_vertices, 0.005),
+        VertexFormat::Lines => &vertex::lines_as_triangles(unformatted_vertices, &stroke),
         VertexFormat::Triangles => unformatted_vertices,
+        VertexFormat::Points => &vertex::points_as_triangles(unformatted_vertices, POINT_SIZE),
     };
-    vertex::index(raw_vertices);
+    index(raw_vertices, weld);
+
+    log::info!("completed benchmark in {:?}", now.elapsed());
+}
+
+/// index `vertices`, welding them if a tolerance is given
+fn index(vertices: &[Vertex], weld: Option<f64>) {
+    if let Some(tolerance) = weld {
+        vertex::index_welded(vertices, tolerance);
+    } else {
+        vertex::index(vertices);
+    }
+}
+
+/// evaluate every pixel of a full hd image, as escape-time fractals have no iterations to compute
+pub fn run_escape_time(escape_time: &EscapeTime) {
+    log::info!("starting benchmark");
+    let now = std::time::Instant::now();
+
+    escape_time.rasterize(1920, 1080);
 
     log::info!("completed benchmark in {:?}", now.elapsed());
 }
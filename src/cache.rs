@@ -0,0 +1,40 @@
+use fractals::{curves::cache::Cache, vertex::estimate::mib};
+
+/// print all cached iterations with their size
+pub fn list(cache: &Cache) {
+    let entries = match cache.entries() {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::error!("could not read {}: {e}", cache.directory().display());
+            std::process::exit(1);
+        },
+    };
+
+    for entry in &entries {
+        let header = &entry.header;
+        let parameters = if header.parameters.is_empty() { String::new() } else { format!(" ({})", header.parameters) };
+        let stale = if header.version == env!("CARGO_PKG_VERSION") { "" } else { ", stale" };
+        println!(
+            "{}{parameters}, iteration {}: {} vertices, {} MiB{stale}",
+            header.name, header.iteration + 1, header.vertex_count, mib(to_usize(entry.bytes)),
+        );
+    }
+
+    let bytes = entries.iter().map(|entry| entry.bytes).sum::<u64>();
+    println!("{} cached iterations, {} MiB in {}", entries.len(), mib(to_usize(bytes)), cache.directory().display());
+}
+
+/// delete all cached iterations
+pub fn clear(cache: &Cache) {
+    match cache.clear() {
+        Ok((files, bytes)) => println!("deleted {files} cached iterations, {} MiB", mib(to_usize(bytes))),
+        Err(e) => {
+            log::error!("could not clear {}: {e}", cache.directory().display());
+            std::process::exit(1);
+        },
+    }
+}
+
+fn to_usize(bytes: u64) -> usize {
+    bytes.try_into().unwrap_or(usize::MAX)
+}
@@ -0,0 +1,110 @@
+//! coloring of vertices and escape-time fractals, same as in `rendering/shader.wgsl`
+//! and `rendering/escape_time.wgsl` (keep them in sync), for rendering without a gpu
+
+use std::f32::consts::PI;
+
+use crate::vertex::Vertex;
+
+/// linear rgb, all values in range [0, 1]
+pub type Rgb = [f32; 3];
+
+/// number of iterations after which the hue of escape-time fractals repeats
+const ESCAPE_TIME_HUE_CYCLE: f32 = 64.;
+
+/// `max_iteration`: highest iteration value present in the rendered vertices <br>
+/// `animation_value`: in range [0, 1) for color animation
+pub fn vertex_color(vertex: &Vertex, max_iteration: u32, animation_value: f32) -> Rgb {
+    let pos = vertex.position;
+
+    // in range [0, 1]
+    #[allow(clippy::cast_precision_loss)]
+    let scaled_iteration = if max_iteration == 0 {
+        1.
+    } else {
+        vertex.iteration as f32 / max_iteration as f32
+    };
+
+    // in range [-PI, PI], 0 means pointing up (positive y)
+    #[allow(clippy::cast_possible_truncation)]
+    let angle = pos.x.atan2(pos.y) as f32;
+    // in range [0, 1]
+    let scaled_angle = (angle + PI) / (2. * PI);
+
+    // based on angle
+    let mut h = scaled_angle;
+    // rotating clockwise (except on lowest iteration)
+    if scaled_iteration != 0. {
+        h += -animation_value + 1.;
+    }
+    // offset for iteration
+    h += (-scaled_iteration).mul_add(0.3, 0.3);
+    // ensure range [0, 1], like % in wgsl
+    h %= 1.;
+
+    // saturation
+    let s = scale_to(scaled_iteration, 0.8, 1.);
+
+    // luminance
+    let l = scale_to(scaled_iteration, 0.2, 0.55);
+
+    hsl_to_rgb(h, s, l)
+}
+
+/// `iterations`: smooth number of iterations until the point escaped,
+/// `None` meaning it did not (colored black) <br>
+/// `animation_value`: in range [0, 1) for color animation
+pub fn escape_time_color(iterations: Option<f32>, max_iterations: f32, animation_value: f32) -> Rgb {
+    let Some(iterations) = iterations else {
+        return [0.; 3];
+    };
+
+    // in range [0, 1]
+    let scaled_iterations = (iterations / max_iterations).clamp(0., 1.);
+
+    // cycling with the number of iterations
+    let mut h = iterations / ESCAPE_TIME_HUE_CYCLE;
+    // rotating like the curves do
+    h += -animation_value + 1.;
+    // same offset as the lowest iteration of curves
+    h += 0.3;
+    // ensure range [0, 1], like % in wgsl
+    h %= 1.;
+
+    // saturation
+    let s = scale_to(scaled_iterations, 0.8, 1.);
+
+    // luminance, brighter closer to the set
+    let l = scale_to(scaled_iterations.sqrt(), 0., 0.55);
+
+    hsl_to_rgb(h, s, l)
+}
+
+/// all input and output values in range [0, 1]
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> Rgb {
+    let a = s * l.min(1. - l);
+    let helper = |n: f32| {
+        let k = h.mul_add(12., n) % 12.;
+        a.mul_add(-(k - 3.).min(9. - k).clamp(-1., 1.), l)
+    };
+    [helper(0.), helper(8.), helper(4.)]
+}
+
+/// value in range [0, 1]
+fn scale_to(value: f32, min: f32, max: f32) -> f32 {
+    value.mul_add(max - min, min)
+}
+
+/// encode linear `color` as 8 bit srgb,
+/// like writing to an srgb surface does
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn to_srgb8(color: Rgb) -> [u8; 3] {
+    color.map(|c| {
+        let c = c.clamp(0., 1.);
+        let encoded = if c <= 0.003_130_8 {
+            c * 12.92
+        } else {
+            1.055f32.mul_add(c.powf(1. / 2.4), -0.055)
+        };
+        (encoded * 255.).round() as u8
+    })
+}
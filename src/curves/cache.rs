@@ -0,0 +1,530 @@
+//! computed iterations stored on disk, so they do not have to be computed again
+//! after restarting. only used after [`Cache::enable`]
+
+use std::{fs::{self, File}, io::{self, BufReader, BufWriter, Read, Seek, Write}, path::{Path, PathBuf}, sync::{OnceLock, atomic::{AtomicU64, Ordering}}, time::SystemTime};
+
+use super::Curve;
+use crate::vertex::{Vertex, vec2::DVec2};
+
+/// at the start of every file
+const MAGIC: &[u8; 8] = b"FRACTALS";
+/// changes whenever the layout of files changes
+const FORMAT_VERSION: u32 = 1;
+const EXTENSION: &str = "vertices";
+/// iterations with fewer vertices are computed about as fast as they are loaded
+const MIN_VERTICES: usize = 1 << 20;
+/// x and y as f64, iteration as u32
+const VERTEX_BYTES: u64 = 20;
+/// strings in headers longer than this are corrupted
+const MAX_STRING_BYTES: u64 = 1 << 16;
+
+static CACHE: OnceLock<Cache> = OnceLock::new();
+/// for unique names of temporary files within this process
+static TEMPORARY_FILES: AtomicU64 = AtomicU64::new(0);
+
+/// directory with one file per cached iteration, named after the
+/// [key](Curve::cache_key) of the curve and the iteration. the least recently
+/// used files are deleted when all of them together get larger than `max_bytes`
+pub struct Cache {
+    directory: PathBuf,
+    max_bytes: u64,
+}
+
+/// file in a [`Cache`], see [`Cache::entries`]
+pub struct Entry {
+    pub path: PathBuf,
+    pub header: Header,
+    /// of the whole file
+    pub bytes: u64,
+}
+
+/// what a file contains, to validate it before loading the vertices
+pub struct Header {
+    /// of the program that wrote the file
+    pub version: String,
+    /// of the curve, see [`key`]
+    pub key: u64,
+    /// 0 meaning initial state
+    pub iteration: usize,
+    /// type of the curve
+    pub name: String,
+    /// values of the parameters changing vertices
+    pub parameters: String,
+    pub vertex_count: u64,
+    /// of the vertices, see [`Checksum`]
+    pub checksum: u64,
+}
+
+impl Cache {
+    /// `max_bytes` of all files together
+    pub const fn new(directory: PathBuf, max_bytes: u64) -> Self {
+        Self { directory, max_bytes }
+    }
+
+    /// in the cache directory of the user (e.g. `~/.cache/fractals`)
+    pub fn default_directory() -> Option<PathBuf> {
+        dirs::cache_dir().map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+    }
+
+    /// use this cache in [`Curve::try_vertices`] from now on,
+    /// can only be done once
+    pub fn enable(self) {
+        log::debug!("caching iterations in {}", self.directory.display());
+        if CACHE.set(self).is_err() {
+            log::warn!("cache was already enabled");
+        }
+    }
+
+    /// the cache used by [`Curve::try_vertices`], if enabled
+    pub fn enabled() -> Option<&'static Self> {
+        CACHE.get()
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, key: u64, iteration: usize) -> PathBuf {
+        self.directory.join(format!("{key:016x}-{iteration}.{EXTENSION}"))
+    }
+
+    /// `true` if there is a file for `iteration` of the curve with `key`,
+    /// which may still turn out to be invalid when loading it
+    pub fn contains(&self, key: u64, iteration: usize) -> bool {
+        self.path(key, iteration).is_file()
+    }
+
+    /// vertices of `iteration` of `curve` if they are cached.
+    /// files that are stale (written by another version) or corrupted are deleted
+    pub fn load<C: Curve + ?Sized>(&self, curve: &C, iteration: usize) -> Option<Vec<Vertex>> {
+        let key = key(curve);
+        let path = self.path(key, iteration);
+        if !path.is_file() {
+            return None;
+        }
+
+        match read(&path, key, iteration) {
+            Ok(vertices) => {
+                log::info!("loaded iteration {} from {}", iteration + 1, path.display());
+                // mark as recently used
+                if let Err(e) = File::options().append(true).open(&path).and_then(|file| file.set_modified(SystemTime::now())) {
+                    log::warn!("could not update {}: {e}", path.display());
+                }
+                Some(vertices)
+            },
+            Err(e) => {
+                log::warn!("discarding {}: {e}", path.display());
+                if let Err(e) = fs::remove_file(&path) {
+                    log::warn!("could not delete {}: {e}", path.display());
+                }
+                None
+            },
+        }
+    }
+
+    /// write `vertices` of `iteration` of `curve` to a file and delete the least recently used
+    /// files if necessary. iterations too small to be worth it or too large for the cache are skipped
+    pub fn store<C: Curve + ?Sized>(&self, curve: &C, iteration: usize, vertices: &[Vertex]) {
+        let bytes = (vertices.len() as u64).saturating_mul(VERTEX_BYTES);
+        if vertices.len() < MIN_VERTICES || bytes > self.max_bytes {
+            return;
+        }
+
+        let header = Header::new(curve, iteration, vertices);
+        let path = self.path(header.key, iteration);
+
+        // so that other processes never see a partially written file,
+        // named so that processes (and threads) storing the same iteration do not share it
+        let temporary = path.with_extension(format!(
+            "{}-{}.tmp", std::process::id(), TEMPORARY_FILES.fetch_add(1, Ordering::Relaxed),
+        ));
+        let result = fs::create_dir_all(&self.directory)
+            .and_then(|()| write(&temporary, &header, vertices))
+            .and_then(|()| fs::rename(&temporary, &path));
+        match result {
+            Ok(()) => log::info!("cached iteration {} in {}", iteration + 1, path.display()),
+            Err(e) => {
+                log::warn!("could not cache iteration {} in {}: {e}", iteration + 1, path.display());
+                let _ = fs::remove_file(&temporary);
+                return;
+            },
+        }
+
+        if let Err(e) = self.trim() {
+            log::warn!("could not delete old files in {}: {e}", self.directory.display());
+        }
+    }
+
+    /// all files in the cache, ordered by curve and iteration.
+    /// files that cannot be read are logged and skipped
+    pub fn entries(&self) -> io::Result<Vec<Entry>> {
+        let mut entries = self.files()?
+            .into_iter()
+            .filter_map(|(path, bytes, _)| match File::open(&path).and_then(|file| read_header(&mut BufReader::new(file))) {
+                Ok(header) => Some(Entry { path, header, bytes }),
+                Err(e) => {
+                    log::warn!("could not read {}: {e}", path.display());
+                    None
+                },
+            })
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| (&a.header.name, &a.header.parameters, a.header.iteration)
+            .cmp(&(&b.header.name, &b.header.parameters, b.header.iteration)));
+        Ok(entries)
+    }
+
+    /// delete all files in the cache, returns how many and their size in bytes
+    pub fn clear(&self) -> io::Result<(usize, u64)> {
+        let files = self.files()?;
+        let bytes = files.iter().map(|(_, bytes, _)| bytes).sum();
+        for (path, _, _) in &files {
+            fs::remove_file(path)?;
+        }
+        Ok((files.len(), bytes))
+    }
+
+    /// delete the least recently used files until the rest fits into `max_bytes`
+    fn trim(&self) -> io::Result<()> {
+        let mut files = self.files()?;
+        // most recently used first
+        files.sort_by(|(_, _, a), (_, _, b)| b.cmp(a));
+
+        let mut total = 0_u64;
+        for (path, bytes, _) in files {
+            total = total.saturating_add(bytes);
+            if total > self.max_bytes {
+                log::info!("deleting {} to stay below the cache size", path.display());
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// path, size and modification time of all files in the cache
+    fn files(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let entries = match fs::read_dir(&self.directory) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+
+        let mut files = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == EXTENSION) {
+                let metadata = fs::metadata(&path)?;
+                files.push((path, metadata.len(), metadata.modified()?));
+            }
+        }
+        Ok(files)
+    }
+}
+
+impl Header {
+    /// for `vertices` of `iteration` of `curve`, written by this version
+    fn new<C: Curve + ?Sized>(curve: &C, iteration: usize, vertices: &[Vertex]) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            key: key(curve),
+            iteration,
+            name: super::short_type_name::<C>().to_owned(),
+            parameters: curve.parameters().iter()
+                .filter(|p| p.changes_vertices)
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", "),
+            vertex_count: vertices.len() as u64,
+            checksum: Checksum::of(vertices),
+        }
+    }
+}
+
+/// identifies `curve` with its current parameters, see [`Curve::cache_key`]
+pub fn key<C: Curve + ?Sized>(curve: &C) -> u64 {
+    let mut checksum = Checksum::new();
+    checksum.add_str(&curve.cache_key());
+    checksum.add_str(&format!("{:?}", curve.vertex_format()));
+    for parameter in curve.parameters().iter().filter(|p| p.changes_vertices) {
+        checksum.add_str(parameter.name);
+        checksum.add(parameter.value().to_bits().into());
+    }
+    for vertex in &curve.data()[0] {
+        checksum.add_vertex(vertex);
+    }
+    checksum.0
+}
+
+/// FNV-1a over 64-bit words instead of bytes (stable across program versions)
+struct Checksum(u64);
+
+impl Checksum {
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    const fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn of(vertices: &[Vertex]) -> u64 {
+        let mut checksum = Self::new();
+        for vertex in vertices {
+            checksum.add_vertex(vertex);
+        }
+        checksum.0
+    }
+
+    fn add(&mut self, word: u64) {
+        self.0 = (self.0 ^ word).wrapping_mul(Self::PRIME);
+    }
+
+    fn add_str(&mut self, string: &str) {
+        self.add(string.len() as u64);
+        for byte in string.bytes() {
+            self.add(byte.into());
+        }
+    }
+
+    fn add_vertex(&mut self, vertex: &Vertex) {
+        self.add(vertex.position.x.to_bits());
+        self.add(vertex.position.y.to_bits());
+        self.add(vertex.iteration.into());
+    }
+}
+
+fn write(path: &Path, header: &Header, vertices: &[Vertex]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    out.write_all(MAGIC)?;
+    out.write_all(&FORMAT_VERSION.to_le_bytes())?;
+    write_str(&mut out, &header.version)?;
+    out.write_all(&header.key.to_le_bytes())?;
+    out.write_all(&(header.iteration as u64).to_le_bytes())?;
+    write_str(&mut out, &header.name)?;
+    write_str(&mut out, &header.parameters)?;
+    out.write_all(&header.vertex_count.to_le_bytes())?;
+    out.write_all(&header.checksum.to_le_bytes())?;
+    for vertex in vertices {
+        out.write_all(&vertex.position.x.to_le_bytes())?;
+        out.write_all(&vertex.position.y.to_le_bytes())?;
+        out.write_all(&vertex.iteration.to_le_bytes())?;
+    }
+    out.into_inner()?.sync_all()
+}
+
+fn write_str(out: &mut impl Write, string: &str) -> io::Result<()> {
+    out.write_all(&(string.len() as u64).to_le_bytes())?;
+    out.write_all(string.as_bytes())
+}
+
+/// vertices of the file at `path`, which has to be for `iteration` of the curve with `key`
+fn read(path: &Path, key: u64, iteration: usize) -> io::Result<Vec<Vertex>> {
+    let file = File::open(path)?;
+    let file_bytes = file.metadata()?.len();
+    let mut input = BufReader::new(file);
+    let header = read_header(&mut input)?;
+
+    if header.version != env!("CARGO_PKG_VERSION") {
+        return Err(invalid(format!("stale, written by version {}", header.version)));
+    }
+    if header.key != key || header.iteration != iteration {
+        return Err(invalid("written for another curve or iteration".to_owned()));
+    }
+    let vertex_bytes = header.vertex_count.saturating_mul(VERTEX_BYTES);
+    if input.stream_position()?.checked_add(vertex_bytes) != Some(file_bytes) {
+        return Err(invalid(format!("corrupted, expected {} vertices", header.vertex_count)));
+    }
+
+    let mut vertices = Vec::with_capacity(header.vertex_count.try_into().unwrap_or_default());
+    let mut bytes = [0; VERTEX_BYTES as usize];
+    for _ in 0..header.vertex_count {
+        input.read_exact(&mut bytes)?;
+        let [x, y] = [0, 8].map(|i| f64::from_le_bytes(bytes[i..i + 8].try_into().unwrap()));
+        let vertex_iteration = u32::from_le_bytes(bytes[16..].try_into().unwrap());
+        vertices.push(Vertex::new(DVec2::new(x, y), vertex_iteration));
+    }
+
+    if Checksum::of(&vertices) != header.checksum {
+        return Err(invalid("corrupted, checksum does not match".to_owned()));
+    }
+    Ok(vertices)
+}
+
+fn read_header(input: &mut impl Read) -> io::Result<Header> {
+    let mut magic = [0; MAGIC.len()];
+    input.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(invalid("not a cached iteration".to_owned()));
+    }
+    let format_version = u32::from_le_bytes(read_array(input)?);
+    if format_version != FORMAT_VERSION {
+        return Err(invalid(format!("stale, unknown format version {format_version}")));
+    }
+
+    Ok(Header {
+        version: read_str(input)?,
+        key: read_u64(input)?,
+        iteration: read_u64(input)?.try_into().map_err(|_| invalid("iteration out of range".to_owned()))?,
+        name: read_str(input)?,
+        parameters: read_str(input)?,
+        vertex_count: read_u64(input)?,
+        checksum: read_u64(input)?,
+    })
+}
+
+fn read_array<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    read_array(input).map(u64::from_le_bytes)
+}
+
+fn read_str(input: &mut impl Read) -> io::Result<String> {
+    let len = read_u64(input)?;
+    if len > MAX_STRING_BYTES {
+        return Err(invalid("corrupted header".to_owned()));
+    }
+    let mut bytes = vec![0; len.try_into().unwrap()];
+    input.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| invalid("corrupted header".to_owned()))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::koch_snowflake::KochSnowflake;
+
+    /// empty directory for a test, deleted again when dropped
+    struct Directory(PathBuf);
+
+    impl Directory {
+        fn new(test: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("fractals-{test}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn cache(&self, max_bytes: u64) -> Cache {
+            Cache::new(self.0.clone(), max_bytes)
+        }
+    }
+
+    impl Drop for Directory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    /// write `iteration` of `curve` to `cache` like [`Cache::store`], but regardless of its size
+    fn store(cache: &Cache, curve: &mut KochSnowflake, iteration: usize) -> PathBuf {
+        let vertices = curve.vertices(iteration).clone();
+        let path = cache.path(key(curve), iteration);
+        write(&path, &Header::new(curve, iteration, &vertices), &vertices).unwrap();
+        path
+    }
+
+    #[test]
+    fn store_and_load() {
+        let directory = Directory::new("store-and-load");
+        let cache = directory.cache(u64::MAX);
+        let curve = KochSnowflake::new();
+        let vertices = (0..MIN_VERTICES as u32)
+            .map(|i| Vertex::new(DVec2::new(f64::from(i), 0.), i))
+            .collect::<Vec<_>>();
+
+        cache.store(&curve, 9, &vertices);
+        assert!(cache.contains(key(&curve), 9));
+        assert_eq!(cache.load(&curve, 9), Some(vertices));
+        // no temporary files left
+        assert_eq!(fs::read_dir(&directory.0).unwrap().count(), 1);
+
+        // too small to be worth it
+        cache.store(&curve, 1, &curve.data()[0]);
+        assert!(!cache.contains(key(&curve), 1));
+    }
+
+    #[test]
+    fn corrupted_files_are_discarded() {
+        let directory = Directory::new("corrupted");
+        let cache = directory.cache(u64::MAX);
+        let mut curve = KochSnowflake::new();
+        let vertices = curve.vertices(3).clone();
+
+        let path = store(&cache, &mut curve, 3);
+        assert_eq!(cache.load(&curve, 3).as_ref(), Some(&vertices));
+
+        // checksum mismatch
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 5;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(cache.load(&curve, 3), None);
+        assert!(!path.exists());
+
+        // truncated, within the vertices and within the header
+        let file_bytes = fs::metadata(store(&cache, &mut curve, 3)).unwrap().len();
+        for len in [file_bytes - 1, 12] {
+            let path = store(&cache, &mut curve, 3);
+            File::options().write(true).open(&path).unwrap().set_len(len).unwrap();
+            assert_eq!(cache.load(&curve, 3), None);
+            assert!(!path.exists());
+        }
+
+        // written by another version
+        let path = cache.path(key(&curve), 3);
+        let header = Header { version: "0.0.0".to_owned(), ..Header::new(&curve, 3, &vertices) };
+        write(&path, &header, &vertices).unwrap();
+        assert_eq!(cache.load(&curve, 3), None);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn other_parameters_or_iterations_miss() {
+        let directory = Directory::new("other-key");
+        let cache = directory.cache(u64::MAX);
+        let mut curve = KochSnowflake::new();
+        let old_key = key(&curve);
+        let old_path = store(&cache, &mut curve, 2);
+
+        // width-divisor
+        curve.set_parameter(0, 4.);
+        let new_key = key(&curve);
+        assert_ne!(new_key, old_key);
+        assert!(!cache.contains(new_key, 2));
+        assert_eq!(cache.load(&curve, 2), None);
+        assert!(old_path.exists());
+
+        // file of the old parameters (or another iteration) in place of the new one
+        for (key, iteration) in [(new_key, 2), (old_key, 3)] {
+            let path = cache.path(key, iteration);
+            fs::copy(&old_path, &path).unwrap();
+            curve.set_parameter(0, if key == new_key { 4. } else { 3. });
+            assert_eq!(cache.load(&curve, iteration), None);
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn trim_deletes_least_recently_used() {
+        let directory = Directory::new("trim");
+        let mut curve = KochSnowflake::new();
+        let paths = (0..4)
+            .map(|iteration| store(&directory.cache(u64::MAX), &mut curve, iteration))
+            .collect::<Vec<_>>();
+        // iteration 1 used least recently, then 0, 3, 2
+        for (path, seconds) in paths.iter().zip([1, 0, 3, 2]) {
+            let modified = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000 + seconds);
+            File::options().append(true).open(path).unwrap().set_modified(modified).unwrap();
+        }
+        let bytes = |i: usize| fs::metadata(&paths[i]).unwrap().len();
+
+        // room for all but the least recently used one
+        directory.cache(bytes(0) + bytes(2) + bytes(3)).trim().unwrap();
+        assert_eq!(paths.iter().map(|path| path.exists()).collect::<Vec<_>>(), [true, false, true, true]);
+    }
+}
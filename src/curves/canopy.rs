@@ -1,84 +1,80 @@
 use rayon::prelude::*;
-use std::f32::consts::PI;
+use std::f64::consts::PI;
 
-use super::Curve;
-use crate::rendering::vertex::{Vertex, VertexFormat, vec2::Vec2};
+use super::{Curve, parameter::{Parameter, Unit}};
+use crate::vertex::{Vertex, VertexFormat, stroke::{Cap, Stroke}, vec2::DVec2};
 
 /// <https://en.wikipedia.org/wiki/Fractal_canopy>
 pub struct Canopy {
     data: Vec<Vec<Vertex>>,
-    /// factor of PI
-    left_angle: f32,
-    /// factor of PI
-    right_angle: f32,
+    parameters: [Parameter; 5],
 }
 
 impl Canopy {
-    // adjust shape
-    const LENGTH_FACTOR: f32 = 0.675;
-    /// factor of PI
-    const INITIAL_LEFT_ANGLE: f32 = 0.2;
-    /// factor of PI
-    const INITIAL_RIGHT_ANGLE: f32 = 0.35;
-    /// when adjusting angle
-    const ANGLE_INCREMENT: f32 = 0.05;
-    const ANGLE_MIN: f32 = Self::ANGLE_INCREMENT;
-    const ANGLE_MAX: f32 = 0.5;
-}
-
-impl Canopy {
-    /// returns `true` if value was actually changed <br>
-    /// `!increment == decrement` <br>
-    /// `!left == right`
-    pub fn change_angle(&mut self, increment: bool, left: bool) -> bool {
-        let angle = if left {
-            &mut self.left_angle
-        } else {
-            &mut self.right_angle
-        };
+    // indices of parameters
+    const LEFT_ANGLE: usize = 0;
+    const RIGHT_ANGLE: usize = 1;
+    const LENGTH_FACTOR: usize = 2;
+    const WIDTH_FALLOFF: usize = 3;
+    const MIN_WIDTH: usize = 4;
 
-        *angle += if increment {
-            Self::ANGLE_INCREMENT
-        } else {
-            -Self::ANGLE_INCREMENT
-        };
-
-        let clamped_angle = angle.clamp(Self::ANGLE_MIN, Self::ANGLE_MAX);
-        // changed in range
-        let changed = (*angle - clamped_angle).abs() < Self::ANGLE_INCREMENT;
-
-        if changed {
-            log::info!("set {} angle to {:.2}π", if left { "left" } else { "right" }, angle);
-        } else {
-            // ensure range
-            *angle = clamped_angle;
-        }
-
-        changed
-    }
-}
-
-impl Curve for Canopy {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
-            left_angle: Self::INITIAL_LEFT_ANGLE,
-            right_angle: Self::INITIAL_RIGHT_ANGLE,
+            parameters: [
+                // name, unit, default, min, max, step
+                Parameter::new("left-angle",    Unit::FactorOfPi, 0.2,   0.05, 0.5,  0.05),
+                Parameter::new("right-angle",   Unit::FactorOfPi, 0.35,  0.05, 0.5,  0.05),
+                Parameter::new("length-factor", Unit::None,       0.675, 0.05, 0.95, 0.025),
+                Parameter::width_falloff(0.8),
+                Parameter::min_width(0.001),
+            ],
             // always pointing counterclockwise to make rotation work later
             // in this case: always point top to bottom
             data: vec![vec![
-                Vertex::new(Vec2::new(0., -0.25), 0),
-                Vertex::new(Vec2::new(0., -0.75), 0),
+                Vertex::new(DVec2::new(0., -0.25), 0),
+                Vertex::new(DVec2::new(0., -0.75), 0),
             ]],
         }
     }
+}
+
+impl Default for Canopy {
+    fn default() -> Self { Self::new() }
+}
 
+impl Curve for Canopy {
     fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
     fn default_iteration(&self) -> usize { 11 }
     
     fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
     fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
 
+    fn     parameters(&    self) -> &    [Parameter] { &    self.parameters }
+    fn mut_parameters(&mut self) -> &mut [Parameter] { &mut self.parameters }
+
+    /// thick trunk with branches getting thinner like in a real tree
+    fn stroke(&self) -> Stroke {
+        Stroke {
+            width: 0.02,
+            falloff: f64::from(self.parameters[Self::WIDTH_FALLOFF].value()),
+            min_width: f64::from(self.parameters[Self::MIN_WIDTH].value()),
+            cap: Cap::Round,
+            ..Stroke::DEFAULT
+        }
+    }
+
+    /// every line of the last iteration gets two new lines on top,
+    /// so `2^(iteration + 1) - 1` lines
+    fn vertex_count(&self, iteration: usize) -> usize {
+        let lines = 2usize.saturating_pow(iteration.saturating_add(1).try_into().unwrap_or(u32::MAX)) - 1;
+        lines.saturating_mul(2)
+    }
+
     fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        let left_angle = PI * f64::from(self.parameters[Self::LEFT_ANGLE].value());
+        let right_angle = PI * f64::from(self.parameters[Self::RIGHT_ANGLE].value());
+        let length_factor = f64::from(self.parameters[Self::LENGTH_FACTOR].value());
+
         last_vertices
             .par_chunks(2)
             // just lines with highest iteration 
@@ -89,8 +85,8 @@ impl Curve for Canopy {
 
                 let bottom_to_top = top - bottom;
 
-                let top_left = top + (bottom_to_top.rotate_ccw(PI * self.left_angle) * Self::LENGTH_FACTOR);
-                let top_right = top + (bottom_to_top.rotate_cw(PI * self.right_angle) * Self::LENGTH_FACTOR);
+                let top_left = top + (bottom_to_top.rotate_ccw(left_angle) * length_factor);
+                let top_right = top + (bottom_to_top.rotate_cw(right_angle) * length_factor);
 
                 [
                     Vertex::new(top_left, iteration),
@@ -0,0 +1,226 @@
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+
+use super::{Curve, parameter::{Parameter, Unit}};
+use crate::vertex::{Vertex, VertexFormat, affine::Affine, vec2::DVec2};
+
+/// points of the initial state
+const INITIAL_POINTS: usize = 1024;
+/// steps to take before the initial points, until the point is (close to) on the attractor
+const TRANSIENT_STEPS: usize = 32;
+/// points computed sequentially from one starting point, with their own random numbers
+const POINTS_PER_WALK: usize = 4096;
+
+/// affine map of an [iterated function system](https://en.wikipedia.org/wiki/Iterated_function_system)
+/// with the probability of choosing it
+#[derive(Clone, Copy, Debug)]
+pub struct Map {
+    /// should be contractive, otherwise the points diverge
+    pub affine: Affine,
+    /// relative to the other maps
+    pub probability: f32,
+}
+
+/// <https://en.wikipedia.org/wiki/Chaos_game>
+///
+/// points of the attractor of an iterated function system, found by
+/// repeatedly applying a randomly chosen map to the last point.
+/// every iteration doubles the number of points.
+/// random numbers only depend on the seed, so results are reproducible.
+pub struct ChaosGame {
+    data: Vec<Vec<Vertex>>,
+    /// transformed to fit the attractor into the window
+    maps: Vec<Map>,
+    /// upper bound of the random number choosing each map, the last one being 1
+    cumulative_probabilities: Vec<f32>,
+    parameters: [Parameter; 1],
+    default_iteration: usize,
+}
+
+impl ChaosGame {
+    // indices of parameters
+    const SEED: usize = 0;
+
+    /// `maps` must not be empty, `seed` for random numbers
+    /// (as a parameter, so it has to fit into an `f32` exactly)
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(maps: Vec<Map>, seed: u32, default_iteration: usize) -> Self {
+        let total_probability = maps.iter().map(|m| m.probability).sum::<f32>();
+        let cumulative_probabilities = maps.iter()
+            .scan(0., |sum, map| {
+                *sum += map.probability / total_probability;
+                Some(*sum)
+            })
+            .collect::<Vec<_>>();
+
+        let mut curve = Self {
+            data: vec![],
+            maps,
+            cumulative_probabilities,
+            parameters: [
+                // name, unit, default, min, max, step
+                Parameter::new("seed", Unit::Integer, seed as f32, 0., 16_777_216., 1.),
+            ],
+            default_iteration,
+        };
+
+        // fit the initial points into the window, which the following ones will be close to
+        let points = curve.initial_points().collect::<Vec<_>>();
+        let (min, max) = points.iter().fold(
+            (DVec2::new(f64::MAX, f64::MAX), DVec2::new(f64::MIN, f64::MIN)),
+            |(min, max), p| (DVec2::new(min.x.min(p.x), min.y.min(p.y)), DVec2::new(max.x.max(p.x), max.y.max(p.y))),
+        );
+        let scale = 1.6 / (max.x - min.x).max(max.y - min.y);
+        let center = min + ((max - min) / 2.);
+        let fit = Affine::scale(scale, -(center * scale));
+
+        // same maps, but in the coordinates of the window
+        if let Some(unfit) = fit.inverse() {
+            for map in &mut curve.maps {
+                map.affine = fit.after(&map.affine).after(&unfit);
+            }
+        }
+
+        curve.reset();
+        curve
+    }
+
+    /// <https://en.wikipedia.org/wiki/Barnsley_fern>
+    pub fn barnsley_fern() -> Self {
+        let map = |matrix, offset: (f64, f64), probability| Map {
+            affine: Affine::new(matrix, DVec2::new(offset.0, offset.1)),
+            probability,
+        };
+
+        Self::new(vec![
+            map([[ 0.,    0.  ], [ 0.,   0.16]], (0., 0.  ), 0.01),
+            map([[ 0.85,  0.04], [-0.04, 0.85]], (0., 1.6 ), 0.85),
+            map([[ 0.2,  -0.26], [ 0.23, 0.22]], (0., 1.6 ), 0.07),
+            map([[-0.15,  0.28], [ 0.26, 0.24]], (0., 0.44), 0.07),
+        ], 0, 9)
+    }
+
+    /// <https://en.wikipedia.org/wiki/Chaos_game#Sierpi%C5%84ski_triangle>,
+    /// moving halfway towards a random corner
+    pub fn sierpinski_triangle() -> Self {
+        let corners = [DVec2::new(0., 0.75), DVec2::new(-0.75, -0.75), DVec2::new(0.75, -0.75)];
+        let maps = corners.iter()
+            .map(|&corner| Map { affine: Affine::scale(0.5, corner / 2.), probability: 1. })
+            .collect();
+
+        Self::new(maps, 0, 9)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn seed(&self) -> u64 {
+        self.parameters[Self::SEED].value() as u64
+    }
+
+    /// random numbers for `walk` number `walk` of `iteration`
+    fn rng(&self, iteration: u32, walk: usize) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed());
+        rng.set_stream((u64::from(iteration) << 32) | walk as u64);
+        rng
+    }
+
+    /// `steps` points following `start`
+    fn walk(&self, start: DVec2, steps: usize, mut rng: ChaCha8Rng) -> impl Iterator<Item = DVec2> + '_ {
+        std::iter::successors(Some(start), move |&point| {
+            let random = rng.gen::<f32>();
+            let index = self.cumulative_probabilities.iter()
+                .position(|&p| random < p)
+                .unwrap_or(self.maps.len() - 1);
+            Some(self.maps[index].affine.apply(point))
+        })
+        .skip(1)
+        .take(steps)
+    }
+
+    fn initial_points(&self) -> impl Iterator<Item = DVec2> + '_ {
+        self.walk(DVec2::new(0., 0.), TRANSIENT_STEPS + INITIAL_POINTS, self.rng(0, 0))
+            .skip(TRANSIENT_STEPS)
+    }
+}
+
+impl Curve for ChaosGame {
+    fn vertex_format(&self) -> VertexFormat { VertexFormat::Points }
+    fn default_iteration(&self) -> usize { self.default_iteration }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    fn     parameters(&    self) -> &    [Parameter] { &    self.parameters }
+    fn mut_parameters(&mut self) -> &mut [Parameter] { &mut self.parameters }
+
+    fn cache_key(&self) -> String { format!("ChaosGame {:?}", self.maps) }
+
+    /// every iteration doubles the number of points
+    fn vertex_count(&self, iteration: usize) -> usize {
+        INITIAL_POINTS.saturating_mul(2usize.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    /// the initial state depends on the seed as well
+    fn reset(&mut self) {
+        let points = self.initial_points()
+            .map(|point| Vertex::new(point, 0))
+            .collect();
+        self.data = vec![points];
+    }
+
+    fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        let points = last_vertices.len();
+        let walks = points.div_ceil(POINTS_PER_WALK);
+
+        let new_vertices = (0..walks).into_par_iter()
+            .flat_map_iter(|walk| {
+                // every point is on the attractor, so any of them can be continued
+                let start = last_vertices[walk].position;
+                let steps = POINTS_PER_WALK.min(points - walk * POINTS_PER_WALK);
+                self.walk(start, steps, self.rng(iteration, walk))
+                    .map(move |point| Vertex::new(point, iteration))
+            });
+
+        last_vertices.par_iter()
+            .copied()
+            .chain(new_vertices)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seed_determines_points() {
+        let mut a = ChaosGame::barnsley_fern();
+        let mut b = ChaosGame::barnsley_fern();
+        assert_eq!(a.vertices(3), b.vertices(3));
+
+        b.set_parameter(ChaosGame::SEED, 1.);
+        assert_ne!(a.vertices(3), b.vertices(3));
+        // and back
+        b.set_parameter(ChaosGame::SEED, 0.);
+        assert_eq!(a.vertices(3), b.vertices(3));
+    }
+
+    #[test]
+    fn points_stay_on_attractor() {
+        // fitted into the window, with the following points close to the initial ones
+        let mut barnsley_fern = ChaosGame::barnsley_fern();
+        assert!(barnsley_fern.vertices(4).iter().all(|v| v.position.x.abs() < 0.85 && v.position.y.abs() < 0.85));
+
+        // within the (counterclockwise) triangle of the corners, which the maps (scaling by 0.5) keep in place
+        let mut sierpinski_triangle = ChaosGame::sierpinski_triangle();
+        let corners = sierpinski_triangle.maps.iter()
+            .map(|map| map.affine.offset * 2.)
+            .collect::<Vec<_>>();
+        let cross = |a: DVec2, b: DVec2, p: DVec2| (b.x - a.x).mul_add(p.y - a.y, -((b.y - a.y) * (p.x - a.x)));
+        for vertex in sierpinski_triangle.vertices(4) {
+            for i in 0..3 {
+                assert!(cross(corners[i], corners[(i + 1) % 3], vertex.position) > -1e-9, "{vertex:?}");
+            }
+        }
+    }
+}
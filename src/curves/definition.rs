@@ -0,0 +1,450 @@
+use rayon::prelude::*;
+use std::{collections::HashMap, fmt, ops::Range, path::{Path, PathBuf}, sync::Arc};
+use toml::Spanned;
+
+use super::{Curve, chaos_game::{ChaosGame, Map}};
+use crate::vertex::{Vertex, VertexFormat, affine::Affine, stroke::{Cap, Join, Stroke}, vec2::DVec2};
+
+/// file extension of definition files in the config directory
+const EXTENSION: &str = "toml";
+
+/// a fractal loaded from a definition file, e.g.
+/// ```toml
+/// name = "Koch curve"
+/// vertex-format = "lines" # or "triangles"
+/// default-iteration = 5
+/// # optional: keep lines/triangles of previous iterations
+/// # and only replace the ones of the last iteration
+/// keep-previous = false
+///
+/// # initial state, two vertices per line or three per triangle
+/// vertices = [[-0.75, 0.0], [0.75, 0.0]]
+///
+/// # what each line/triangle is replaced with, referencing points by name.
+/// # new points are tagged with the new iteration, corners keep their iteration
+/// replacement = [
+///     ["a", "third-a"], ["third-a", "top"], ["top", "third-b"], ["third-b", "b"],
+/// ]
+///
+/// # points relative to each line/triangle that is replaced.
+/// # lines: corners `a`, `b`, x-axis from `a` to `b`,
+/// #        y-axis clockwise orthogonal to the x-axis (same length)
+/// # triangles: corners `a`, `b`, `c`, x-axis from `a` to `b`, y-axis from `a` to `c`
+/// [points]
+/// third-a = [0.3333, 0.0]
+/// top     = [0.5,    0.25]
+/// third-b = [0.6667, 0.0]
+///
+/// # optional, only for lines: how they are drawn, every field is optional
+/// [stroke]
+/// width = 0.005
+/// falloff = 1.0 # width is multiplied by this for every iteration
+/// min-width = 0.0 # limit for the falloff
+/// join = "miter" # or "bevel", "round"
+/// miter-limit = 4.0
+/// cap = "butt" # or "square", "round"
+/// ```
+/// or a [`ChaosGame`] with points instead, e.g.
+/// ```toml
+/// name = "Sierpinski carpet"
+/// vertex-format = "points"
+/// default-iteration = 9
+/// # optional: for random numbers, changing the result slightly
+/// seed = 0
+///
+/// # affine maps `(x, y) -> matrix * (x, y) + offset`, one of which is applied to
+/// # the last point to get the next one. they are chosen randomly, with probabilities
+/// # relative to each other. the result is scaled to fit into the window.
+/// [[maps]]
+/// matrix = [[0.3333, 0.0], [0.0, 0.3333]]
+/// offset = [0.0, 0.0]
+/// probability = 1.0
+/// ```
+pub struct Definition {
+    pub name: String,
+    vertex_format: VertexFormat,
+    default_iteration: usize,
+    keep_previous: bool,
+    vertices: Vec<Vertex>,
+    /// in the same order as `replacement` references them
+    points: Vec<Point>,
+    /// flattened indices into `points`, each group of two/three forming a line/triangle
+    replacement: Vec<usize>,
+    /// only with vertex format lines
+    stroke: Stroke,
+    /// only with vertex format points
+    maps: Vec<Map>,
+    seed: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Point {
+    /// index of a corner of the replaced line/triangle
+    Corner(usize),
+    /// coordinates relative to the replaced line/triangle
+    New(DVec2),
+}
+
+/// error while loading a definition file, pointing to where it occurred
+#[derive(Debug)]
+pub struct Error {
+    path: PathBuf,
+    /// line and column, starting at 1
+    location: Option<(usize, usize)>,
+    message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{}:{line}:{column}: {}", self.path.display(), self.message),
+            None => write!(f, "{}: {}", self.path.display(), self.message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// structure of the file itself, before validation
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct File {
+    name: String,
+    vertex_format: Spanned<String>,
+    default_iteration: usize,
+    keep_previous: Option<Spanned<bool>>,
+    vertices: Option<Spanned<Vec<[f64; 2]>>>,
+    #[serde(default)]
+    points: HashMap<Spanned<String>, [f64; 2]>,
+    replacement: Option<Spanned<Vec<PointNames>>>,
+    maps: Option<Spanned<Vec<MapFile>>>,
+    seed: Option<Spanned<u32>>,
+    stroke: Option<Spanned<StrokeFile>>,
+}
+
+/// element of `replacement` in [`File`], forming a line/triangle
+type PointNames = Spanned<Vec<Spanned<String>>>;
+
+/// element of `maps` in [`File`]
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct MapFile {
+    matrix: [[f64; 2]; 2],
+    offset: [f64; 2],
+    probability: Spanned<f32>,
+}
+
+/// `stroke` in [`File`], missing fields as in [`Stroke::DEFAULT`]
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct StrokeFile {
+    width: Option<Spanned<f64>>,
+    falloff: Option<Spanned<f64>>,
+    min_width: Option<Spanned<f64>>,
+    join: Option<Join>,
+    miter_limit: Option<Spanned<f64>>,
+    cap: Option<Cap>,
+}
+
+impl Definition {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let text = std::fs::read_to_string(path).map_err(|e| Error {
+            path: path.to_owned(),
+            location: None,
+            message: e.to_string(),
+        })?;
+
+        let error = |span: Option<Range<usize>>, message: String| Error {
+            path: path.to_owned(),
+            location: span.map(|span| location(&text, span.start)),
+            message,
+        };
+
+        let file: File = toml::from_str(&text)
+            .map_err(|e| error(e.span(), e.message().to_owned()))?;
+
+        let (vertex_format, corners): (_, &[&str]) = match file.vertex_format.get_ref().as_str() {
+            "lines" => (VertexFormat::Lines, &["a", "b"]),
+            "triangles" => (VertexFormat::Triangles, &["a", "b", "c"]),
+            "points" => (VertexFormat::Points, &[]),
+            other => return Err(error(Some(file.vertex_format.span()),
+                format!("unknown vertex format '{other}', expected 'lines', 'triangles' or 'points'"))),
+        };
+        let group_size = corners.len();
+
+        // fields used by the other vertex formats only
+        let mut unused = match vertex_format {
+            VertexFormat::Points => vec![
+                ("vertices", file.vertices.as_ref().map(Spanned::span)),
+                ("replacement", file.replacement.as_ref().map(Spanned::span)),
+                ("points", file.points.keys().next().map(Spanned::span)),
+                ("keep-previous", file.keep_previous.as_ref().map(Spanned::span)),
+            ],
+            VertexFormat::Lines | VertexFormat::Triangles => vec![
+                ("maps", file.maps.as_ref().map(Spanned::span)),
+                ("seed", file.seed.as_ref().map(Spanned::span)),
+            ],
+        };
+        if vertex_format != VertexFormat::Lines {
+            unused.push(("stroke", file.stroke.as_ref().map(Spanned::span)));
+        }
+        if let Some((name, span)) = unused.into_iter().find(|(_, span)| span.is_some()) {
+            return Err(error(span, format!("'{name}' is not used with vertex format {}", file.vertex_format.get_ref())));
+        }
+
+        if vertex_format == VertexFormat::Points {
+            return Self::load_chaos_game(file, error);
+        }
+
+        let missing = |name| error(None, format!("missing field `{name}`"));
+        let vertices = file.vertices.ok_or_else(|| missing("vertices"))?;
+        let replacement_groups = file.replacement.ok_or_else(|| missing("replacement"))?;
+
+        if vertices.get_ref().is_empty() || !vertices.get_ref().len().is_multiple_of(group_size) {
+            return Err(error(Some(vertices.span()), format!(
+                "expected a positive multiple of {group_size} vertices for {}, found {}",
+                file.vertex_format.get_ref(), vertices.get_ref().len(),
+            )));
+        }
+
+        if replacement_groups.get_ref().is_empty() {
+            return Err(error(Some(replacement_groups.span()), format!(
+                "expected at least one element of replacement, otherwise every {} would disappear",
+                if vertex_format == VertexFormat::Lines { "line" } else { "triangle" },
+            )));
+        }
+
+        // corners first, then new points in order of appearance in the file
+        let mut point_names = corners.iter()
+            .map(|&name| name.to_owned())
+            .collect::<Vec<_>>();
+        let mut points = (0..group_size).map(Point::Corner).collect::<Vec<_>>();
+        let mut new_points = file.points.into_iter().collect::<Vec<_>>();
+        new_points.sort_by_key(|(name, _)| name.span().start);
+        for (name, [x, y]) in new_points {
+            if point_names.contains(name.get_ref()) {
+                return Err(error(Some(name.span()), format!("point '{}' is already defined", name.get_ref())));
+            }
+            point_names.push(name.into_inner());
+            points.push(Point::New(DVec2::new(x, y)));
+        }
+
+        let mut replacement = vec![];
+        for group in replacement_groups.get_ref() {
+            if group.get_ref().len() != group_size {
+                return Err(error(Some(group.span()), format!(
+                    "expected {group_size} points per element of replacement for {}, found {}",
+                    file.vertex_format.get_ref(), group.get_ref().len(),
+                )));
+            }
+
+            for name in group.get_ref() {
+                let index = point_names.iter()
+                    .position(|n| n == name.get_ref())
+                    .ok_or_else(|| error(Some(name.span()), format!(
+                        "unknown point '{}', expected one of: {}",
+                        name.get_ref(), point_names.join(", "),
+                    )))?;
+                replacement.push(index);
+            }
+        }
+
+        Ok(Self {
+            name: file.name,
+            vertex_format,
+            default_iteration: file.default_iteration,
+            keep_previous: file.keep_previous.is_some_and(Spanned::into_inner),
+            vertices: vertices.into_inner().into_iter()
+                .map(|[x, y]| Vertex::new(DVec2::new(x, y), 0))
+                .collect(),
+            points,
+            replacement,
+            stroke: match file.stroke {
+                Some(stroke) => Self::load_stroke(stroke.into_inner(), error)?,
+                None => Stroke::DEFAULT,
+            },
+            maps: vec![],
+            seed: 0,
+        })
+    }
+
+    /// `stroke` of [`File`], with missing fields as in [`Stroke::DEFAULT`]
+    fn load_stroke(file: StrokeFile, error: impl Fn(Option<Range<usize>>, String) -> Error) -> Result<Stroke, Error> {
+        let at_least = |value: Option<Spanned<f64>>, min: f64, default: f64, expected: &str| match value {
+            Some(value) if !(value.get_ref().is_finite() && *value.get_ref() >= min) =>
+                Err(error(Some(value.span()), format!("expected {expected}, found {}", value.get_ref()))),
+            Some(value) => Ok(value.into_inner()),
+            None => Ok(default),
+        };
+
+        Ok(Stroke {
+            width: at_least(file.width, f64::MIN_POSITIVE, Stroke::DEFAULT.width, "a positive width")?,
+            falloff: at_least(file.falloff, f64::MIN_POSITIVE, Stroke::DEFAULT.falloff, "a positive falloff")?,
+            min_width: at_least(file.min_width, 0., Stroke::DEFAULT.min_width, "a non-negative min. width")?,
+            join: file.join.unwrap_or(Stroke::DEFAULT.join),
+            miter_limit: at_least(file.miter_limit, 1., Stroke::DEFAULT.miter_limit, "a miter limit of at least 1")?,
+            cap: file.cap.unwrap_or(Stroke::DEFAULT.cap),
+        })
+    }
+
+    /// rest of [`Self::load`] for vertex format points
+    fn load_chaos_game(file: File, error: impl Fn(Option<Range<usize>>, String) -> Error) -> Result<Self, Error> {
+        let maps = file.maps.ok_or_else(|| error(None, "missing field `maps`".to_owned()))?;
+        if maps.get_ref().is_empty() {
+            return Err(error(Some(maps.span()), "expected at least one map".to_owned()));
+        }
+
+        let maps = maps.into_inner().into_iter()
+            .map(|map| {
+                let probability = *map.probability.get_ref();
+                if !(probability.is_finite() && probability > 0.) {
+                    return Err(error(Some(map.probability.span()),
+                        format!("expected a positive probability, found {probability}")));
+                }
+
+                let [x, y] = map.offset;
+                Ok(Map { affine: Affine::new(map.matrix, DVec2::new(x, y)), probability })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            name: file.name,
+            vertex_format: VertexFormat::Points,
+            default_iteration: file.default_iteration,
+            keep_previous: false,
+            stroke: Stroke::DEFAULT,
+            vertices: vec![],
+            points: vec![],
+            replacement: vec![],
+            maps,
+            seed: file.seed.map(Spanned::into_inner).unwrap_or_default(),
+        })
+    }
+
+    /// curve following this definition
+    pub fn new_instance(self: &Arc<Self>) -> Box<dyn Curve> {
+        match self.vertex_format {
+            VertexFormat::Points => Box::new(ChaosGame::new(self.maps.clone(), self.seed, self.default_iteration)),
+            VertexFormat::Lines | VertexFormat::Triangles => Box::new(DefinedCurve::new(self.clone())),
+        }
+    }
+
+    /// load all given files and all files in the config directory,
+    /// logging the ones that could not be loaded
+    pub fn load_all(paths: &[PathBuf]) -> Vec<Arc<Self>> {
+        let config_dir_paths = dirs::config_dir()
+            .map(|dir| dir.join(env!("CARGO_PKG_NAME")))
+            .and_then(|dir| std::fs::read_dir(dir).ok())
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|e| e == EXTENSION));
+
+        paths.iter().cloned()
+            .chain(config_dir_paths)
+            .filter_map(|path| match Self::load(&path) {
+                Ok(definition) => {
+                    log::info!("loaded '{}' from {}", definition.name, path.display());
+                    Some(Arc::new(definition))
+                },
+                Err(e) => {
+                    log::error!("{e}");
+                    None
+                },
+            })
+            .collect()
+    }
+}
+
+/// line and column (starting at 1) of byte `offset` in `text`
+fn location(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or_default().chars().count() + 1;
+    (line, column)
+}
+
+/// curve defined by a [`Definition`] with lines or triangles
+pub struct DefinedCurve {
+    definition: Arc<Definition>,
+    data: Vec<Vec<Vertex>>,
+}
+
+impl DefinedCurve {
+    pub fn new(definition: Arc<Definition>) -> Self {
+        Self {
+            data: vec![definition.vertices.clone()],
+            definition,
+        }
+    }
+
+    /// replacement of one line/triangle
+    fn replace(&self, group: &[Vertex], iteration: u32) -> impl Iterator<Item = Vertex> + '_ {
+        let origin = group[0].position;
+        let (x_axis, y_axis) = match self.definition.vertex_format {
+            VertexFormat::Lines => {
+                let a_to_b = group[1].position - origin;
+                (a_to_b, a_to_b.clockwise_orthogonal())
+            },
+            VertexFormat::Triangles => (group[1].position - origin, group[2].position - origin),
+            VertexFormat::Points => unreachable!("definitions with points are chaos games"),
+        };
+
+        let points = self.definition.points.iter()
+            .map(|point| match *point {
+                Point::Corner(index) => group[index],
+                Point::New(DVec2 { x, y }) => Vertex::new(origin + (x_axis * x) + (y_axis * y), iteration),
+            })
+            .collect::<Vec<_>>();
+
+        self.definition.replacement.iter().map(move |&index| points[index])
+    }
+}
+
+impl Curve for DefinedCurve {
+    fn vertex_format(&self) -> VertexFormat { self.definition.vertex_format }
+    fn default_iteration(&self) -> usize { self.definition.default_iteration }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    fn stroke(&self) -> Stroke { self.definition.stroke }
+
+    fn cache_key(&self) -> String {
+        let definition = &self.definition;
+        format!("DefinedCurve {} {:?} {:?}", definition.keep_previous, definition.points, definition.replacement)
+    }
+
+    /// exact without `keep-previous`, extrapolated otherwise
+    fn vertex_count(&self, iteration: usize) -> usize {
+        if self.definition.keep_previous {
+            return super::extrapolated_vertex_count(self, iteration);
+        }
+
+        // every line/triangle is replaced with the same number of lines/triangles
+        let group_size = self.definition.vertex_format.group_size();
+        let growth = self.definition.replacement.len() / group_size;
+        self.data[0].len().saturating_mul(growth.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    /// not with `keep-previous`, which keeps lines/triangles of previous iterations
+    fn replaces_groups(&self) -> bool { !self.definition.keep_previous }
+
+    fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        let group_size = self.definition.vertex_format.group_size();
+        let keep_previous = self.definition.keep_previous;
+
+        let replaced = last_vertices
+            .par_chunks(group_size)
+            // with keep_previous: just the ones with highest iteration
+            .filter(|group| !keep_previous || group.iter().any(|v| v.iteration == iteration - 1))
+            .flat_map_iter(|group| self.replace(group, iteration));
+
+        if keep_previous {
+            replaced.chain(last_vertices.par_iter().copied()).collect()
+        } else {
+            replaced.collect()
+        }
+    }
+}
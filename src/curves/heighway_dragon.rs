@@ -0,0 +1,64 @@
+use rayon::prelude::*;
+
+use super::Curve;
+use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
+
+/// <https://en.wikipedia.org/wiki/Dragon_curve#Heighway_dragon>
+pub struct HeighwayDragon {
+    data: Vec<Vec<Vertex>>
+}
+
+impl HeighwayDragon {
+    pub fn new() -> Self {
+        Self {
+            data: vec![vec![
+                Vertex::new(DVec2::new(-0.45, -0.18), 0),
+                Vertex::new(DVec2::new( 0.65, -0.18), 0),
+            ]],
+        }
+    }
+}
+
+impl Default for HeighwayDragon {
+    fn default() -> Self { Self::new() }
+}
+
+/// replace each line `a -> b` with `a -> middle` and `b -> middle`, `middle` forming
+/// a right angle to the left of the line. the second line being reversed makes the
+/// next iteration fold to alternating sides along the curve.
+pub(super) fn next_iteration(last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+    last_vertices.par_chunks(2).map(|line| {
+        let (a, b) = (line[0].position, line[1].position);
+        let (a_iter, b_iter) = (line[0].iteration, line[1].iteration);
+
+        let a_to_b = b - a;
+        let middle = a + (a_to_b / 2.) + (-a_to_b.clockwise_orthogonal() / 2.);
+
+        [
+            Vertex::new(a,      a_iter),
+            Vertex::new(middle, iteration),
+
+            Vertex::new(b,      b_iter),
+            Vertex::new(middle, iteration),
+        ]
+    }).flatten().collect()
+}
+
+impl Curve for HeighwayDragon {
+    fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
+    fn default_iteration(&self) -> usize { 12 }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    /// every line is replaced with 2 lines
+    fn vertex_count(&self, iteration: usize) -> usize {
+        self.data[0].len().saturating_mul(2usize.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    fn replaces_groups(&self) -> bool { true }
+
+    fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        next_iteration(last_vertices, iteration)
+    }
+}
@@ -1,56 +1,79 @@
 use rayon::prelude::*;
 
-use super::Curve;
-use crate::rendering::vertex::{Vertex, VertexFormat, vec2::Vec2};
+use super::{Curve, parameter::{Parameter, Unit}};
+use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
 
 /// <https://en.wikipedia.org/wiki/Koch_snowflake>
 pub struct KochSnowflake {
-    data: Vec<Vec<Vertex>>
+    data: Vec<Vec<Vertex>>,
+    parameters: [Parameter; 2],
 }
 
 impl KochSnowflake {
-    // adjust shape
-    const WIDTH_DIVISOR: f32 = 3.;
-    const HEIGHT_DIVISOR: f32 = 4.;
-}
+    // indices of parameters
+    const WIDTH_DIVISOR: usize = 0;
+    const HEIGHT_DIVISOR: usize = 1;
 
-impl Curve for KochSnowflake {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
+            parameters: [
+                // name, unit, default, min, max, step
+                Parameter::new("width-divisor",  Unit::None, 3., 2.,  6., 0.25),
+                Parameter::new("height-divisor", Unit::None, 4., 1., 10., 0.25),
+            ],
             // always pointing counterclockwise to make orthogonals work later
             data: vec![vec![
-                Vertex::new(Vec2::new(-0.75, -0.45), 0),
-                Vertex::new(Vec2::new( 0.75, -0.45), 0),
+                Vertex::new(DVec2::new(-0.75, -0.45), 0),
+                Vertex::new(DVec2::new( 0.75, -0.45), 0),
 
-                Vertex::new(Vec2::new( 0.75, -0.45), 0),
-                Vertex::new(Vec2::new( 0.00,  0.75), 0),
+                Vertex::new(DVec2::new( 0.75, -0.45), 0),
+                Vertex::new(DVec2::new( 0.00,  0.75), 0),
 
-                Vertex::new(Vec2::new( 0.00,  0.75), 0),
-                Vertex::new(Vec2::new(-0.75, -0.45), 0),
+                Vertex::new(DVec2::new( 0.00,  0.75), 0),
+                Vertex::new(DVec2::new(-0.75, -0.45), 0),
             ]],
         }
     }
+}
 
+impl Default for KochSnowflake {
+    fn default() -> Self { Self::new() }
+}
+
+impl Curve for KochSnowflake {
     fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
     fn default_iteration(&self) -> usize { 5 }
     
     fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
     fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
 
+    fn     parameters(&    self) -> &    [Parameter] { &    self.parameters }
+    fn mut_parameters(&mut self) -> &mut [Parameter] { &mut self.parameters }
+
+    /// every line is replaced with 4 lines
+    fn vertex_count(&self, iteration: usize) -> usize {
+        self.data[0].len().saturating_mul(4usize.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    fn replaces_groups(&self) -> bool { true }
+
     fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        let width_divisor = f64::from(self.parameters[Self::WIDTH_DIVISOR].value());
+        let height_divisor = f64::from(self.parameters[Self::HEIGHT_DIVISOR].value());
+
         last_vertices.par_chunks(2).map(|line| {
             let (a, b) = (line[0].position, line[1].position);
             let (a_iter, b_iter) = (line[0].iteration, line[1].iteration);
 
             let a_to_b = b - a;
 
-            let third_a = a + ( a_to_b / Self::WIDTH_DIVISOR);
-            let third_b = b + (-a_to_b / Self::WIDTH_DIVISOR);
+            let third_a = a + ( a_to_b / width_divisor);
+            let third_b = b + (-a_to_b / width_divisor);
 
             let top = {
                 // this orthogonal always points in the right direction,
                 // because our (initial) lines are counter-clockwise
-                let up = a_to_b.clockwise_orthogonal() / Self::HEIGHT_DIVISOR;
+                let up = a_to_b.clockwise_orthogonal() / height_divisor;
 
                 a + (a_to_b / 2.) + up
             };
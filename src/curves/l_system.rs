@@ -0,0 +1,347 @@
+use rayon::{iter::Either, prelude::*};
+use std::collections::{BTreeMap, HashMap};
+use std::f64::consts::PI;
+
+use super::{Curve, parameter::{Parameter, Unit}};
+use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
+
+/// <https://en.wikipedia.org/wiki/L-system>
+///
+/// symbols understood by the turtle, all others are ignored when drawing:
+/// - `F`, `G`: move forward and draw a line
+/// - `f`: move forward without drawing
+/// - `+`: turn counterclockwise by the left angle
+/// - `-`: turn clockwise by the right angle
+/// - `|`: turn around
+/// - `[`, `]`: push/pop position, direction and step length
+/// - `>`, `<`: multiply/divide step length by the step scale
+pub struct LSystem {
+    data: Vec<Vec<Vertex>>,
+    parameters: [Parameter; 3],
+    axiom: String,
+    rules: HashMap<char, String>,
+    /// where each turtle starts and the direction and length of its first step.
+    /// every turtle draws the whole axiom, one after the other
+    turtles: Vec<(DVec2, DVec2)>,
+    /// factor the initial step length is multiplied with on every iteration,
+    /// to keep the size of the curve the same
+    shrink: f64,
+    default_iteration: usize,
+}
+
+impl LSystem {
+    // indices of parameters
+    const LEFT_ANGLE: usize = 0;
+    const RIGHT_ANGLE: usize = 1;
+    const STEP_SCALE: usize = 2;
+}
+
+/// symbol of the rewritten axiom
+#[derive(Clone, Copy, Debug)]
+struct Symbol {
+    char: char,
+    /// iteration of the vertex the turtle is at before this symbol
+    start: u32,
+    /// iteration of the vertex the turtle is at after this symbol,
+    /// only different from `start` for lines
+    end: u32,
+}
+
+/// state of the turtle drawing the curve
+#[derive(Clone, Copy)]
+struct Turtle {
+    position: DVec2,
+    /// iteration of the vertex at the current position
+    position_iteration: u32,
+    /// direction and length of the next step
+    step: DVec2,
+}
+
+impl LSystem {
+    /// angles as factor of PI
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_rules(
+        axiom: &str,
+        rules: &[(char, &str)],
+        turtles: &[(DVec2, DVec2)],
+        shrink: f64,
+        (left_angle, right_angle): (f32, f32),
+        step_scale: f32,
+        default_iteration: usize,
+    ) -> Self {
+        let mut l_system = Self {
+            parameters: [
+                // name, unit, default, min, max, step
+                Parameter::new("left-angle",  Unit::FactorOfPi, left_angle,  0., 1., 0.01),
+                Parameter::new("right-angle", Unit::FactorOfPi, right_angle, 0., 1., 0.01),
+                Parameter::new("step-scale",  Unit::None,       step_scale,  0.05, 1., 0.025),
+            ],
+            axiom: axiom.to_owned(),
+            rules: rules.iter()
+                .map(|(symbol, replacement)| (*symbol, (*replacement).to_owned()))
+                .collect(),
+            turtles: turtles.to_vec(),
+            shrink,
+            default_iteration,
+            data: vec![],
+        };
+        l_system.reset();
+        l_system
+    }
+
+    /// same shape and iterations as [`super::koch_snowflake::KochSnowflake`]
+    /// with a `height-divisor` of `2 * sqrt(3)`, one turtle for each side of its triangle
+    pub fn koch_snowflake() -> Self {
+        let corners = [DVec2::new(-0.75, -0.45), DVec2::new(0.75, -0.45), DVec2::new(0., 0.75)];
+        let sides = (0..3)
+            .map(|i| (corners[i], corners[(i + 1) % 3] - corners[i]))
+            .collect::<Vec<_>>();
+
+        Self::with_rules(
+            "F",
+            &[('F', "F-F++F-F")],
+            &sides,
+            1. / 3.,
+            (1. / 3., 1. / 3.),
+            1.,
+            5,
+        )
+    }
+
+    /// same shape and iterations as [`super::canopy::Canopy`]
+    pub fn canopy() -> Self {
+        Self::with_rules(
+            "FX",
+            &[('X', "[+>FX][->FX]")],
+            &[(DVec2::new(0., -0.75), DVec2::new(0., 0.5))],
+            1.,
+            (0.2, 0.35),
+            0.675,
+            11,
+        )
+    }
+
+    /// <https://en.wikipedia.org/wiki/L-system#Example_7:_fractal_plant>,
+    /// but with lines keeping their length (and iteration) instead of doubling
+    pub fn fractal_plant() -> Self {
+        Self::with_rules(
+            "F>X",
+            &[('X', "F+[[>X]->X]-F[-F>X]+>X")],
+            &[(DVec2::new(-0.6, -0.9), DVec2::new(0.18, 0.525))],
+            1.,
+            (0.14, 0.14),
+            0.5,
+            6,
+        )
+    }
+
+    /// rewrite the axiom `iteration` times
+    fn symbols(&self, iteration: u32) -> Vec<Symbol> {
+        let mut symbols = self.axiom.chars()
+            .map(|char| Symbol { char, start: 0, end: 0 })
+            .collect::<Vec<_>>();
+
+        for current_iteration in 1 ..= iteration {
+            symbols = symbols.par_iter()
+                .flat_map_iter(|&symbol| {
+                    match self.rules.get(&symbol.char) {
+                        Some(replacement) => Either::Left(replace(symbol, replacement, current_iteration).into_iter()),
+                        // symbols without rule stay the same
+                        None => Either::Right(std::iter::once(symbol)),
+                    }
+                })
+                .collect();
+        }
+
+        symbols
+    }
+
+    /// number of lines drawn after rewriting the axiom `iteration` times,
+    /// without actually rewriting it
+    fn line_count(&self, iteration: usize) -> usize {
+        let mut counts = HashMap::<char, usize>::new();
+        for symbol in self.axiom.chars() {
+            *counts.entry(symbol).or_default() += 1;
+        }
+
+        for _ in 0..iteration {
+            let mut next_counts = HashMap::new();
+            for (&symbol, &count) in &counts {
+                match self.rules.get(&symbol) {
+                    Some(replacement) => for replacement_symbol in replacement.chars() {
+                        let next_count = next_counts.entry(replacement_symbol).or_default();
+                        *next_count = count.saturating_add(*next_count);
+                    },
+                    None => {
+                        let next_count = next_counts.entry(symbol).or_default();
+                        *next_count = count.saturating_add(*next_count);
+                    },
+                }
+            }
+            counts = next_counts;
+        }
+
+        ['F', 'G'].iter()
+            .filter_map(|symbol| counts.get(symbol))
+            .fold(0, |sum, &count| sum.saturating_add(count))
+    }
+
+    /// draw `symbols` with every turtle, see [`replace`] for the iterations of the vertices
+    fn interpret(&self, symbols: &[Symbol], iteration: u32) -> Vec<Vertex> {
+        let left_angle = PI * f64::from(self.parameters[Self::LEFT_ANGLE].value());
+        let right_angle = PI * f64::from(self.parameters[Self::RIGHT_ANGLE].value());
+        let step_scale = f64::from(self.parameters[Self::STEP_SCALE].value());
+
+        #[allow(clippy::cast_possible_wrap)]
+        let shrink = self.shrink.powi(iteration as i32);
+        let mut vertices = vec![];
+
+        for &(position, step) in &self.turtles {
+            let mut turtle = Turtle { position, position_iteration: 0, step: step * shrink };
+            let mut stack = vec![];
+
+            for symbol in symbols {
+                match symbol.char {
+                    'F' | 'G' => {
+                        let end = turtle.position + turtle.step;
+                        vertices.push(Vertex::new(turtle.position, turtle.position_iteration));
+                        vertices.push(Vertex::new(end, symbol.end));
+                        turtle.position = end;
+                        turtle.position_iteration = symbol.end;
+                    },
+                    'f' => turtle.position = turtle.position + turtle.step,
+                    '+' => turtle.step = turtle.step.rotate_ccw(left_angle),
+                    '-' => turtle.step = turtle.step.rotate_cw(right_angle),
+                    '|' => turtle.step = -turtle.step,
+                    '[' => stack.push(turtle),
+                    ']' => turtle = stack.pop().expect("unbalanced brackets in l-system"),
+                    '>' => turtle.step = turtle.step * step_scale,
+                    '<' => turtle.step = turtle.step / step_scale,
+                    _ => (),
+                }
+            }
+        }
+
+        vertices
+    }
+}
+
+/// `replacement` of `symbol`, produced in `iteration`. lines in it end at a vertex of
+/// `iteration`, except for replacements of lines: the ones from the start to the end of
+/// the replaced line (outside of brackets) subdivide it, so that vertices in its first half
+/// keep the iteration of its start, the ones in its second half the one of its end and
+/// only a vertex in the middle is new (like for [`super::koch_snowflake::KochSnowflake`])
+fn replace(symbol: Symbol, replacement: &str, iteration: u32) -> Vec<Symbol> {
+    let is_line = matches!(symbol.char, 'F' | 'G');
+    // lines from the start to the end of the replaced line
+    let (mut depth, mut path_lines) = (0_usize, 0);
+    for char in replacement.chars() {
+        match char {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            'F' | 'G' if depth == 0 => path_lines += 1,
+            _ => (),
+        }
+    }
+
+    let (mut vertex_iteration, mut stack, mut path_line) = (symbol.start, vec![], 0);
+    replacement.chars()
+        .map(|char| {
+            let start = vertex_iteration;
+            match char {
+                'F' | 'G' if is_line && stack.is_empty() => {
+                    path_line += 1;
+                    vertex_iteration = match (2 * path_line).cmp(&path_lines) {
+                        std::cmp::Ordering::Less => symbol.start,
+                        std::cmp::Ordering::Equal => iteration,
+                        std::cmp::Ordering::Greater => symbol.end,
+                    };
+                },
+                'F' | 'G' => vertex_iteration = iteration,
+                '[' => stack.push(vertex_iteration),
+                ']' => vertex_iteration = stack.pop().unwrap_or(vertex_iteration),
+                _ => (),
+            }
+            Symbol { char, start, end: vertex_iteration }
+        })
+        .collect()
+}
+
+impl Curve for LSystem {
+    fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
+    fn default_iteration(&self) -> usize { self.default_iteration }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    fn     parameters(&    self) -> &    [Parameter] { &    self.parameters }
+    fn mut_parameters(&mut self) -> &mut [Parameter] { &mut self.parameters }
+
+    fn cache_key(&self) -> String {
+        let rules = self.rules.iter().collect::<BTreeMap<_, _>>();
+        format!("LSystem {} {rules:?} {:?} {}", self.axiom, self.turtles, self.shrink)
+    }
+
+    /// starting iteration depends on parameters as well, so recompute it
+    fn reset(&mut self) {
+        self.data = vec![self.interpret(&self.symbols(0), 0)];
+    }
+
+    fn vertex_count(&self, iteration: usize) -> usize {
+        self.line_count(iteration).saturating_mul(2)
+    }
+
+    fn next_iteration(&self, _last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        // cheap compared to drawing, so just rewrite from the start
+        self.interpret(&self.symbols(iteration), iteration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::{canopy::Canopy, koch_snowflake::KochSnowflake};
+
+    /// lines of `vertices` regardless of their order and direction,
+    /// with positions rounded to hide differences from floating point rounding.
+    /// order and direction differ for the canopy
+    fn lines(vertices: &[Vertex]) -> Vec<[(i64, i64, u32); 2]> {
+        #[allow(clippy::cast_possible_truncation)]
+        let round = |v: &Vertex| ((v.position.x * 1e6).round() as i64, (v.position.y * 1e6).round() as i64, v.iteration);
+        let mut lines = vertices.chunks_exact(2)
+            .map(|line| {
+                let mut line = [round(&line[0]), round(&line[1])];
+                line.sort_unstable();
+                line
+            })
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        lines
+    }
+
+    #[test]
+    fn koch_snowflake_matches_hand_written() {
+        let mut l_system = LSystem::koch_snowflake();
+        let mut hand_written = KochSnowflake::new();
+        #[allow(clippy::cast_possible_truncation)]
+        hand_written.set_parameter(1, (2. * 3_f64.sqrt()) as f32);
+        // same order, but the divisor is only close to 2 * sqrt(3) as f32
+        for iteration in 0..5 {
+            let (l_system, hand_written) = (l_system.vertices(iteration), hand_written.vertices(iteration));
+            assert_eq!(l_system.len(), hand_written.len());
+            for (a, b) in l_system.iter().zip(hand_written) {
+                assert!((a.position - b.position).len() < 1e-6, "iteration {iteration}: {a:?} != {b:?}");
+                assert_eq!(a.iteration, b.iteration, "iteration {iteration}: {a:?} != {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn canopy_matches_hand_written() {
+        let mut l_system = LSystem::canopy();
+        let mut hand_written = Canopy::new();
+        for iteration in 0..8 {
+            assert_eq!(lines(l_system.vertices(iteration)), lines(hand_written.vertices(iteration)), "iteration {iteration}");
+        }
+    }
+}
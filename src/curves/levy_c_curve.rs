@@ -0,0 +1,59 @@
+use rayon::prelude::*;
+
+use super::Curve;
+use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
+
+/// <https://en.wikipedia.org/wiki/L%C3%A9vy_C_curve>
+pub struct LevyCCurve {
+    data: Vec<Vec<Vertex>>
+}
+
+impl LevyCCurve {
+    pub fn new() -> Self {
+        Self {
+            data: vec![vec![
+                Vertex::new(DVec2::new(-0.4, -0.3), 0),
+                Vertex::new(DVec2::new( 0.4, -0.3), 0),
+            ]],
+        }
+    }
+}
+
+impl Default for LevyCCurve {
+    fn default() -> Self { Self::new() }
+}
+
+impl Curve for LevyCCurve {
+    fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
+    fn default_iteration(&self) -> usize { 12 }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    /// every line is replaced with 2 lines
+    fn vertex_count(&self, iteration: usize) -> usize {
+        self.data[0].len().saturating_mul(2usize.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    fn replaces_groups(&self) -> bool { true }
+
+    fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        last_vertices.par_chunks(2).map(|line| {
+            let (a, b) = (line[0].position, line[1].position);
+            let (a_iter, b_iter) = (line[0].iteration, line[1].iteration);
+
+            // unlike the heighway dragon, both new lines keep the direction
+            // of the old one, so they always fold to the same side
+            let a_to_b = b - a;
+            let middle = a + (a_to_b / 2.) + (-a_to_b.clockwise_orthogonal() / 2.);
+
+            [
+                Vertex::new(a,      a_iter),
+                Vertex::new(middle, iteration),
+
+                Vertex::new(middle, iteration),
+                Vertex::new(b,      b_iter),
+            ]
+        }).flatten().collect()
+    }
+}
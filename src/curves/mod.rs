@@ -1,89 +1,444 @@
+pub mod cache;
 pub mod canopy;
+pub mod chaos_game;
+pub mod definition;
+pub mod heighway_dragon;
 pub mod koch_snowflake;
+pub mod l_system;
+pub mod levy_c_curve;
+pub mod self_similar;
 pub mod sierpinski_triangle;
+pub mod space_filling;
+pub mod stream;
+pub mod twindragon;
+pub mod parameter;
 
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
 use strum::IntoEnumIterator;
 
-use crate::rendering::vertex::{Vertex, VertexFormat};
+use crate::escape_time::EscapeTime;
+use cache::Cache;
+use crate::vertex::{Vertex, VertexFormat, estimate::Estimate, instanced::Instancing, stroke::Stroke};
+use definition::Definition;
+use parameter::Parameter;
+use stream::DepthFirst;
 
-#[derive(Default, PartialEq, Eq, Clone, strum::EnumIter, clap::ValueEnum)]
+#[derive(Default, PartialEq, Eq, Clone, strum::EnumIter)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
 pub enum Curves {
     #[default] // first rendered on program start
     Canopy,
     KochSnowflake,
     SierpinskiTriangle,
+    FractalPlant,
+    HeighwayDragon,
+    Twindragon,
+    LevyCCurve,
+    HilbertCurve,
+    PeanoCurve,
+    MooreCurve,
+    GosperCurve,
+    BarnsleyFern,
+    SierpinskiChaosGame,
+    SierpinskiCarpet,
+    VicsekFractal,
+    HTree,
+    PythagorasTree,
+    Mandelbrot,
+    JuliaSet,
 }
 impl Curves {
-    pub fn new_instance(&self) -> Box<dyn Curve> {
-        match self {
+    pub fn new_instance(&self) -> Fractal {
+        let curve: Box<dyn Curve> = match self {
             Self::Canopy => Box::new(canopy::Canopy::new()),
             Self::KochSnowflake => Box::new(koch_snowflake::KochSnowflake::new()),
             Self::SierpinskiTriangle => Box::new(sierpinski_triangle::SierpinskiTriangle::new()),
+            Self::FractalPlant => Box::new(l_system::LSystem::fractal_plant()),
+            Self::HeighwayDragon => Box::new(heighway_dragon::HeighwayDragon::new()),
+            Self::Twindragon => Box::new(twindragon::Twindragon::new()),
+            Self::LevyCCurve => Box::new(levy_c_curve::LevyCCurve::new()),
+            Self::HilbertCurve => Box::new(space_filling::SpaceFillingCurve::hilbert()),
+            Self::PeanoCurve => Box::new(space_filling::SpaceFillingCurve::peano()),
+            Self::MooreCurve => Box::new(space_filling::SpaceFillingCurve::moore()),
+            Self::GosperCurve => Box::new(space_filling::SpaceFillingCurve::gosper()),
+            Self::BarnsleyFern => Box::new(chaos_game::ChaosGame::barnsley_fern()),
+            Self::SierpinskiChaosGame => Box::new(chaos_game::ChaosGame::sierpinski_triangle()),
+            Self::SierpinskiCarpet => Box::new(self_similar::SelfSimilar::sierpinski_carpet()),
+            Self::VicsekFractal => Box::new(self_similar::SelfSimilar::vicsek_fractal()),
+            Self::HTree => Box::new(self_similar::SelfSimilar::h_tree()),
+            Self::PythagorasTree => Box::new(self_similar::SelfSimilar::pythagoras_tree()),
+            Self::Mandelbrot => return Fractal::EscapeTime(EscapeTime::mandelbrot()),
+            Self::JuliaSet => return Fractal::EscapeTime(EscapeTime::julia()),
+        };
+        Fractal::Curve(curve)
+    }
+}
+
+/// instance of any fractal, as they are represented quite differently
+pub enum Fractal {
+    /// represented by vertices
+    Curve(Box<dyn Curve>),
+    /// evaluated per pixel
+    EscapeTime(EscapeTime),
+}
+
+impl Fractal {
+    pub fn parameters(&self) -> &[Parameter] {
+        match self {
+            Self::Curve(curve) => curve.parameters(),
+            Self::EscapeTime(escape_time) => escape_time.parameters(),
+        }
+    }
+
+    pub fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameters().iter().position(|p| p.name == name)
+    }
+
+    /// see [`Curve::set_parameter`]
+    pub fn set_parameter(&mut self, index: usize, value: f32) -> bool {
+        match self {
+            Self::Curve(curve) => curve.set_parameter(index, value),
+            Self::EscapeTime(escape_time) => escape_time.set_parameter(index, value),
+        }
+    }
+
+    /// see [`Curve::step_parameter`]
+    pub fn step_parameter(&mut self, index: usize, increment: bool) -> bool {
+        match self {
+            Self::Curve(curve) => curve.step_parameter(index, increment),
+            Self::EscapeTime(escape_time) => escape_time.step_parameter(index, increment),
+        }
+    }
+}
+
+/// all curves to cycle through:
+/// built-in ones followed by ones loaded from definition files
+pub struct Registry {
+    entries: Vec<Entry>,
+    current: usize,
+}
+
+enum Entry {
+    BuiltIn(Curves),
+    Defined(Arc<Definition>),
+}
+
+impl Registry {
+    pub fn new(definitions: Vec<Arc<Definition>>) -> Self {
+        let entries = Curves::iter()
+            .map(Entry::BuiltIn)
+            .chain(definitions.into_iter().map(Entry::Defined))
+            .collect::<Vec<_>>();
+
+        let current = entries.iter()
+            .position(|e| matches!(e, Entry::BuiltIn(c) if *c == Curves::default()))
+            .unwrap();
+
+        Self { entries, current }
+    }
+
+    pub fn new_instance(&self) -> Fractal {
+        match &self.entries[self.current] {
+            Entry::BuiltIn(curve) => curve.new_instance(),
+            Entry::Defined(definition) => Fractal::Curve(definition.new_instance()),
         }
     }
 
     pub fn next(&mut self) {
-        let mut cycle = Self::iter().cycle();
-        while cycle.next().unwrap() != *self {}
-        *self = cycle.next().unwrap();
+        self.current = (self.current + 1) % self.entries.len();
     }
 
     pub fn prev(&mut self) {
-        let mut cycle = Self::iter().cycle();
-        let mut next1 = cycle.next().unwrap();
-        let mut next2 = cycle.next().unwrap();
-        while next2 != *self {
-            next1 = next2;
-            next2 = cycle.next().unwrap();
-        }
-        *self = next1;
+        self.current = (self.current + self.entries.len() - 1) % self.entries.len();
     }
 }
 
 /// <https://en.wikipedia.org/wiki/Fractal_curve>
-pub trait Curve: std::any::Any {
-    fn new() -> Self
-        where Self: Sized; // for dyn-compatability
-
+pub trait Curve: Send {
     // technically dont need to be methods, but are easier to work with
     fn vertex_format(&self) -> VertexFormat;
     fn default_iteration(&self) -> usize;
 
     fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex>;
 
-    /// one element for each iteration
+    /// one element for each iteration, empty if not computed (anymore), see [`Self::evict`].
+    /// the initial state (iteration 0) is always there
     fn     data(&    self) -> &    Vec<Vec<Vertex>>;
     fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>>;
 
-    /// cast to current type of curve
-    fn downcast(curve: &mut Box<dyn Curve>) -> &mut Self
-        where Self: Sized // for dyn-compatability
-    {
-        (&mut **curve as &mut dyn std::any::Any).downcast_mut::<Self>().unwrap()
+    /// values that change the shape of the curve, none by default
+    fn     parameters(&    self) -> &    [Parameter] { &    [] }
+    fn mut_parameters(&mut self) -> &mut [Parameter] { &mut [] }
+
+    fn parameter_index(&self, name: &str) -> Option<usize> {
+        self.parameters().iter().position(|p| p.name == name)
+    }
+
+    /// returns `true` if value was actually changed,
+    /// which also discards all computed iterations (if it changes vertices)
+    fn set_parameter(&mut self, index: usize, value: f32) -> bool {
+        let changed = self.mut_parameters()[index].set(value);
+        if changed && self.parameters()[index].changes_vertices {
+            self.reset();
+        }
+        changed
+    }
+
+    /// `!increment == decrement` <br>
+    /// returns `true` if value was actually changed,
+    /// which also discards all computed iterations (if it changes vertices)
+    fn step_parameter(&mut self, index: usize, increment: bool) -> bool {
+        let changed = self.mut_parameters()[index].step(increment);
+        if changed && self.parameters()[index].changes_vertices {
+            self.reset();
+        }
+        changed
+    }
+
+    /// number of vertices of `iteration` without computing it (iteration 0 meaning initial state).
+    /// exact for computed iterations, otherwise extrapolated from the growth between
+    /// the last two computed iterations, so curves should override this if they can do better
+    fn vertex_count(&self, iteration: usize) -> usize {
+        extrapolated_vertex_count(self, iteration)
+    }
+
+    /// describes how later iterations are computed, to tell curves apart in the [`Cache`]
+    /// (together with the initial state and the parameters changing vertices).
+    /// the type name by default, so types constructed in different ways (e.g. with other rules)
+    /// have to override this
+    fn cache_key(&self) -> String {
+        std::any::type_name::<Self>().to_owned()
+    }
+
+    /// how lines are represented as triangles, only used with [`VertexFormat::Lines`]
+    fn stroke(&self) -> Stroke {
+        Stroke::DEFAULT
+    }
+
+    /// expected size of `iteration` represented as indexed triangles
+    fn estimate(&self, iteration: usize) -> Estimate {
+        let computed_vertices = (0..self.data().len())
+            .filter(|&i| i != iteration)
+            .map(|i| self.data()[i].len())
+            .fold(0, usize::saturating_add);
+        // iterations in between are kept as well, unless streaming
+        let start = self.closest_computed(iteration);
+        let intermediate_vertices = if self.replaces_groups() {
+            0
+        } else {
+            (start + 1..iteration)
+                .map(|i| self.vertex_count(i))
+                .fold(0, usize::saturating_add)
+        };
+        let stored_vertices = computed_vertices.saturating_add(intermediate_vertices);
+
+        Estimate::new(
+            self.vertex_format(),
+            self.vertex_count(iteration),
+            stored_vertices.saturating_mul(std::mem::size_of::<Vertex>()),
+            &self.stroke(),
+        )
+    }
+
+    /// alternative to computing every vertex: iterations as copies of a base mesh,
+    /// drawn as instances. `None` (default) if the curve cannot be represented like that
+    fn instancing(&self) -> Option<Instancing> {
+        None
+    }
+
+    /// like [`Self::estimate`], but for drawing instances if the curve supports [`Self::instancing`]
+    fn instanced_estimate(&self, iteration: usize) -> Estimate {
+        self.instancing()
+            .map_or_else(|| self.estimate(iteration), |instancing| instancing.estimate(iteration))
+    }
+
+    /// `true` if [`Self::next_iteration`] replaces every group of vertices (line/triangle/point)
+    /// independently of the others (and of its position), so that iterations
+    /// can be computed depth-first, see [`Self::stream`]. `false` by default
+    fn replaces_groups(&self) -> bool {
+        false
+    }
+
+    /// vertices of `iteration` in chunks, computed depth-first from the closest computed iteration
+    /// before it, so without keeping intermediate iterations (or all of this one, if the chunks are
+    /// not kept either). `None` if the curve does not [replace groups](Self::replaces_groups)
+    fn stream(&self, iteration: usize) -> Option<Box<dyn Iterator<Item = Vec<Vertex>> + '_>> {
+        if !self.replaces_groups() {
+            return None;
+        }
+        let start = self.closest_computed(iteration);
+        Some(Box::new(DepthFirst::new(self, start, &self.data()[start], iteration)))
+    }
+
+    /// `true` if the vertices of `iteration` are in data
+    fn is_computed(&self, iteration: usize) -> bool {
+        iteration == 0 || self.data().get(iteration).is_some_and(|vertices| !vertices.is_empty())
+    }
+
+    /// highest computed iteration up to `iteration`
+    fn closest_computed(&self, iteration: usize) -> usize {
+        (0..=iteration).rev().find(|&i| self.is_computed(i)).unwrap()
+    }
+
+    /// compute `iteration` from the one before (which has to be computed) and put it into data.
+    /// returns `false` without changing data if `cancel` was set meanwhile. <br>
+    /// curves that need more than the last vertices to compute the next iteration
+    /// (e.g. orientation of each line) can override this to keep it alongside data
+    fn compute_iteration(&mut self, iteration: usize, cancel: &AtomicBool) -> bool {
+        let vertices = self.next_iteration(&self.data()[iteration - 1], iteration.try_into().unwrap());
+
+        if cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        store_iteration(self.mut_data(), iteration, vertices);
+        true
+    }
+
+    /// remove data except for starting iteration
+    fn reset(&mut self) {
+        self.mut_data().truncate(1);
+    }
+
+    /// free the vertices of computed iterations, except for the starting iteration and `keep`
+    fn evict(&mut self, keep: &[usize]) {
+        evict_iterations(self.mut_data(), keep);
     }
 
     /// iteration 0 meaning initial state
     fn vertices(&mut self, iteration: usize) -> &Vec<Vertex> {
+        self.try_vertices(iteration, &AtomicBool::new(false)).unwrap()
+    }
+
+    /// like [`Self::vertices`], but returns `None` as soon as `cancel` is set.
+    /// iterations computed until then are kept, the one in progress is discarded. <br>
+    /// computes `iteration` from the closest computed one before it, depth-first if the curve
+    /// [replaces groups](Self::replaces_groups), otherwise keeping the iterations in between.
+    /// if the [`Cache`] is enabled, the highest cached iteration up to `iteration` is loaded
+    /// instead of computing it, and `iteration` is cached after computing it
+    fn try_vertices(&mut self, iteration: usize, cancel: &AtomicBool) -> Option<&Vec<Vertex>> {
+        let cache = Cache::enabled().filter(|_| !self.is_computed(iteration));
+        if let Some(cache) = cache {
+            let key = cache::key(self);
+            let loaded = (self.closest_computed(iteration) + 1 ..= iteration).rev()
+                .filter(|&i| cache.contains(key, i))
+                .find_map(|i| cache.load(self, i).map(|vertices| (i, vertices)));
+            if let Some((loaded_iteration, vertices)) = loaded {
+                store_iteration(self.mut_data(), loaded_iteration, vertices);
+            }
+        }
+        // not when loaded from the cache
+        let store = cache.filter(|_| !self.is_computed(iteration));
+
+        let start = self.closest_computed(iteration);
+
+        // no need to stream if the iteration before is computed already
+        if self.replaces_groups() && iteration > start + 1 {
+            log::debug!("computing iteration {} depth-first from iteration {}", iteration + 1, start + 1);
+            let mut vertices = Vec::with_capacity(self.vertex_count(iteration));
+            for chunk in self.stream(iteration).unwrap() {
+                if cancel.load(Ordering::Relaxed) {
+                    log::info!("cancelled computing iteration {}", iteration + 1);
+                    return None;
+                }
+                vertices.extend(chunk);
+            }
+            store_iteration(self.mut_data(), iteration, vertices);
+        }
+
         // compute fractal iterations (if not done already)
-        for current_iteration in self.data().len() ..= iteration {
+        for current_iteration in self.closest_computed(iteration) + 1 ..= iteration {
             if current_iteration >= 9 {
                 log::debug!("computing iteration {}", current_iteration + 1);
             }
 
-            let vertices = self.next_iteration(
-                &self.data()[current_iteration - 1],
-                current_iteration.try_into().unwrap()
-            );
+            if !self.compute_iteration(current_iteration, cancel) {
+                log::info!("cancelled computing iteration {}", current_iteration + 1);
+                return None;
+            }
+        }
+
+        log::info!("computed iteration {} of {}", iteration + 1, short_type_name::<Self>());
+
+        if let Some(cache) = store {
+            cache.store(self, iteration, &self.data()[iteration]);
+        }
+        Some(&self.data()[iteration])
+    }
+}
+
+/// default of [`Curve::vertex_count`], for curves overriding it only in some cases
+pub fn extrapolated_vertex_count<C: Curve + ?Sized>(curve: &C, iteration: usize) -> usize {
+    let data = curve.data();
+    if curve.is_computed(iteration) {
+        return data[iteration].len();
+    }
+
+    // highest two consecutive computed iterations
+    let Some(last) = (1..data.len()).rev().find(|&i| curve.is_computed(i) && curve.is_computed(i - 1)) else {
+        // nothing to extrapolate from yet, but the first iteration is usually cheap
+        let first_count = curve.next_iteration(&data[0], 1).len();
+        return extrapolate(data[0].len(), first_count, steps(1, iteration));
+    };
+
+    extrapolate(data[last - 1].len(), data[last].len(), steps(last, iteration))
+}
+
+/// name of the type `C` without its path
+fn short_type_name<C: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<C>();
+    name.rsplit_once("::").map_or(name, |(_, name)| name)
+}
+
+/// put `vertices` of `iteration` into `data`, see [`Curve::data`]
+fn store_iteration<T>(data: &mut Vec<Vec<T>>, iteration: usize, vertices: Vec<T>) {
+    if data.len() <= iteration {
+        data.resize_with(iteration + 1, Vec::new);
+    }
+    data[iteration] = vertices;
+}
 
-            self.mut_data().push(vertices);
+/// free the iterations of `data` except for the starting iteration and `keep`, see [`Curve::evict`]
+fn evict_iterations<T>(data: &mut Vec<Vec<T>>, keep: &[usize]) {
+    for (iteration, items) in data.iter_mut().enumerate().skip(1) {
+        if !keep.contains(&iteration) && !items.is_empty() {
+            *items = Vec::new();
         }
+    }
+    while data.len() > 1 && data.last().is_some_and(Vec::is_empty) {
+        data.pop();
+    }
+}
+
+/// from iteration `from` to `to`, negative if going back
+fn steps(from: usize, to: usize) -> i32 {
+    let steps = i32::try_from(to.abs_diff(from)).unwrap_or(i32::MAX);
+    if to < from { -steps } else { steps }
+}
+
+/// `current` after growing `steps` more times like from `previous` to `current`
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn extrapolate(previous: usize, current: usize, steps: i32) -> usize {
+    if previous == 0 || steps == 0 {
+        return current;
+    }
+    let growth = current as f64 / previous as f64;
+    // saturates on overflow
+    (current as f64 * growth.powi(steps)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let fractal_name = std::any::type_name::<Self>()
-            .rsplit_once("::")
-            .unwrap()
-            .1;
-        log::info!("computed iteration {} of {}", iteration + 1, fractal_name);
+    #[test]
+    fn evict_iterations_keeps_start_and_kept() {
+        let mut data = (0..6).map(|i| vec![i; 2]).collect::<Vec<_>>();
+        evict_iterations(&mut data, &[2, 3]);
+        // empty iterations at the end are removed
+        assert_eq!(data, [vec![0, 0], vec![], vec![2, 2], vec![3, 3]]);
 
-        &self.data()[iteration]
+        evict_iterations(&mut data, &[1]);
+        assert_eq!(data, [vec![0, 0]]);
     }
 }
@@ -0,0 +1,83 @@
+use std::fmt;
+
+/// what the value of a parameter is measured in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Unit {
+    /// plain number
+    None,
+    /// whole number, e.g. a count
+    Integer,
+    /// factor of PI, e.g. `0.5` meaning `0.5π`
+    FactorOfPi,
+}
+
+/// a named, range-bounded value that changes the shape of a curve
+#[derive(Clone, Debug)]
+pub struct Parameter {
+    /// in kebab-case, e.g. for use on the command line
+    pub name: &'static str,
+    pub unit: Unit,
+    pub default: f32,
+    pub min: f32,
+    pub max: f32,
+    /// when incrementing/decrementing
+    pub step: f32,
+    /// `false` if the parameter only changes how vertices are drawn,
+    /// so computed iterations can be kept when it changes
+    pub changes_vertices: bool,
+    value: f32,
+}
+
+impl Parameter {
+    pub const fn new(name: &'static str, unit: Unit, default: f32, min: f32, max: f32, step: f32) -> Self {
+        Self { name, unit, default, min, max, step, changes_vertices: true, value: default }
+    }
+
+    /// see [`Self::changes_vertices`]
+    pub const fn drawing_only(mut self) -> Self {
+        self.changes_vertices = false;
+        self
+    }
+
+    /// for [`crate::vertex::stroke::Stroke::falloff`]
+    pub const fn width_falloff(default: f32) -> Self {
+        Self::new("width-falloff", Unit::None, default, 0.5, 1., 0.05).drawing_only()
+    }
+
+    /// for [`crate::vertex::stroke::Stroke::min_width`]
+    pub const fn min_width(default: f32) -> Self {
+        Self::new("min-width", Unit::None, default, 0., 0.01, 0.001).drawing_only()
+    }
+
+    pub const fn value(&self) -> f32 {
+        self.value
+    }
+
+    /// clamps `value` to the range of this parameter.
+    /// returns `true` if value was actually changed
+    pub fn set(&mut self, value: f32) -> bool {
+        let value = value.clamp(self.min, self.max);
+
+        #[allow(clippy::float_cmp)]
+        let changed = value != self.value;
+        self.value = value;
+        changed
+    }
+
+    /// `!increment == decrement` <br>
+    /// returns `true` if value was actually changed
+    pub fn step(&mut self, increment: bool) -> bool {
+        self.set(self.value + if increment { self.step } else { -self.step })
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name.replace('-', " ");
+        match self.unit {
+            Unit::None => write!(f, "{name} = {:.3}", self.value),
+            Unit::Integer => write!(f, "{name} = {}", self.value.round()),
+            Unit::FactorOfPi => write!(f, "{name} = {:.2}π", self.value),
+        }
+    }
+}
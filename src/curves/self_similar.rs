@@ -0,0 +1,245 @@
+use rayon::prelude::*;
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
+use super::{Curve, parameter::Parameter};
+use crate::vertex::{Vertex, VertexFormat, affine::Affine, instanced::Instancing, stroke::Stroke, vec2::DVec2};
+
+/// <https://en.wikipedia.org/wiki/Self-similarity>
+///
+/// every iteration consists of copies of the previous iteration,
+/// each mapped through one of the transforms.
+/// optionally also keeps the base shape, e.g. as the trunk of a tree.
+/// vertices are tagged with the number of transforms they went through.
+pub struct SelfSimilar {
+    data: Vec<Vec<Vertex>>,
+    vertex_format: VertexFormat,
+    /// should be contractive, otherwise the copies grow
+    transforms: Vec<Affine>,
+    keep_base: bool,
+    default_iteration: usize,
+    /// for lines, see [`Self::tapering`]
+    stroke: Stroke,
+    /// width falloff and min. width of lines, if tapering
+    parameters: Vec<Parameter>,
+}
+
+impl SelfSimilar {
+    /// `base` has to consist of whole groups of `vertex_format`
+    pub fn new(
+        vertex_format: VertexFormat,
+        base: &[DVec2],
+        transforms: Vec<Affine>,
+        keep_base: bool,
+        default_iteration: usize,
+    ) -> Self {
+        Self {
+            data: vec![base.iter().map(|&position| Vertex::new(position, 0)).collect()],
+            vertex_format,
+            transforms,
+            keep_base,
+            default_iteration,
+            stroke: Stroke::DEFAULT,
+            parameters: vec![],
+        }
+    }
+
+    /// lines start with `width` and get thinner with every iteration,
+    /// with parameters for the falloff and min. width
+    pub fn tapering(mut self, width: f64, falloff: f32, min_width: f32) -> Self {
+        self.stroke.width = width;
+        self.parameters = vec![Parameter::width_falloff(falloff), Parameter::min_width(min_width)];
+        self
+    }
+
+    /// <https://en.wikipedia.org/wiki/Sierpi%C5%84ski_triangle>,
+    /// same as [`super::sierpinski_triangle::SierpinskiTriangle`]
+    pub fn sierpinski_triangle() -> Self {
+        let corners = [DVec2::new(0., 0.75), DVec2::new(-0.75, -0.75), DVec2::new(0.75, -0.75)];
+        let transforms = corners.iter()
+            .map(|&corner| Affine::scale(0.5, corner / 2.))
+            .collect();
+
+        Self::new(VertexFormat::Triangles, &corners, transforms, false, 7)
+    }
+
+    /// <https://en.wikipedia.org/wiki/Sierpi%C5%84ski_carpet>
+    pub fn sierpinski_carpet() -> Self {
+        // all cells of a 3x3 grid but the center
+        let transforms = Self::grid_cells()
+            .filter(|&cell| cell != DVec2::new(0., 0.))
+            .map(|cell| Affine::scale(1. / 3., cell))
+            .collect();
+
+        Self::new(VertexFormat::Triangles, &Self::square(), transforms, false, 5)
+    }
+
+    /// <https://en.wikipedia.org/wiki/Vicsek_fractal>, cross form
+    pub fn vicsek_fractal() -> Self {
+        // center and edges of a 3x3 grid
+        let transforms = Self::grid_cells()
+            .filter(|&cell| cell.x == 0. || cell.y == 0.)
+            .map(|cell| Affine::scale(1. / 3., cell))
+            .collect();
+
+        Self::new(VertexFormat::Triangles, &Self::square(), transforms, false, 6)
+    }
+
+    /// <https://en.wikipedia.org/wiki/H_tree>
+    pub fn h_tree() -> Self {
+        // halfway between the center and the edges,
+        // so half size copies at the tips fill up the other half
+        let half = 0.375;
+        let base = [
+            // horizontal line in the middle
+            DVec2::new(-half, 0.), DVec2::new(half, 0.),
+            // vertical lines at the ends
+            DVec2::new(-half, -half), DVec2::new(-half, half),
+            DVec2::new( half, -half), DVec2::new( half, half),
+        ];
+        let transforms = [(-half, -half), (-half, half), (half, -half), (half, half)].iter()
+            .map(|&(x, y)| Affine::scale(0.5, DVec2::new(x, y)))
+            .collect();
+
+        Self::new(VertexFormat::Lines, &base, transforms, true, 5).tapering(0.012, 0.7, 0.001)
+    }
+
+    /// <https://en.wikipedia.org/wiki/Pythagoras_tree_(fractal)>
+    pub fn pythagoras_tree() -> Self {
+        // the whole tree is 6 sides wide and 4 sides high
+        let side = 0.28;
+        let bottom_left = DVec2::new(-side / 2., -2. * side);
+        let bottom_right = bottom_left + DVec2::new(side, 0.);
+        let top_left = bottom_left + DVec2::new(0., side);
+        let top_right = bottom_right + DVec2::new(0., side);
+        // tip of the right isosceles triangle on top of the square
+        let apex = top_left + DVec2::new(side / 2., side / 2.);
+
+        let base = [
+            bottom_left, bottom_right, top_right,
+            bottom_left, top_right, top_left,
+        ];
+        // squares on the legs of the triangle, with their bottom edge on the leg
+        let transforms = vec![
+            Affine::rotate_scale( FRAC_PI_4, FRAC_1_SQRT_2, DVec2::new(0., 0.)).mapping(bottom_left, top_left),
+            Affine::rotate_scale(-FRAC_PI_4, FRAC_1_SQRT_2, DVec2::new(0., 0.)).mapping(bottom_left, apex),
+        ];
+
+        Self::new(VertexFormat::Triangles, &base, transforms, true, 12)
+    }
+
+    /// square in the middle of the window, as two counterclockwise triangles
+    fn square() -> [DVec2; 6] {
+        let (min, max) = (-0.75, 0.75);
+        [
+            DVec2::new(min, min), DVec2::new(max, min), DVec2::new(max, max),
+            DVec2::new(min, min), DVec2::new(max, max), DVec2::new(min, max),
+        ]
+    }
+
+    /// centers of the cells of a 3x3 grid over [`Self::square`]
+    fn grid_cells() -> impl Iterator<Item = DVec2> {
+        [-0.5, 0., 0.5].into_iter()
+            .flat_map(|y| [-0.5, 0., 0.5].into_iter().map(move |x| DVec2::new(x, y)))
+    }
+}
+
+impl Curve for SelfSimilar {
+    fn vertex_format(&self) -> VertexFormat { self.vertex_format }
+    fn default_iteration(&self) -> usize { self.default_iteration }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    fn     parameters(&    self) -> &    [Parameter] { &    self.parameters }
+    fn mut_parameters(&mut self) -> &mut [Parameter] { &mut self.parameters }
+
+    fn cache_key(&self) -> String {
+        format!("SelfSimilar {:?} {}", self.transforms, self.keep_base)
+    }
+
+    fn stroke(&self) -> Stroke {
+        match self.parameters.as_slice() {
+            [falloff, min_width] => Stroke {
+                falloff: f64::from(falloff.value()),
+                min_width: f64::from(min_width.value()),
+                ..self.stroke
+            },
+            _ => self.stroke,
+        }
+    }
+
+    /// every iteration has one copy of the last one per transform (plus the base),
+    /// so `base * transforms^iteration` (or `base * (transforms^(iteration + 1) - 1) / (transforms - 1)`)
+    fn vertex_count(&self, iteration: usize) -> usize {
+        let base = self.data[0].len();
+        let transforms = self.transforms.len();
+        let pow = |exponent: usize| transforms.saturating_pow(exponent.try_into().unwrap_or(u32::MAX));
+
+        if !self.keep_base {
+            base.saturating_mul(pow(iteration))
+        } else if transforms <= 1 {
+            base.saturating_mul(transforms.saturating_mul(iteration) + 1)
+        } else {
+            base.saturating_mul((pow(iteration.saturating_add(1)) - 1) / (transforms - 1))
+        }
+    }
+
+    /// only for triangles, as lines and points should keep their width in smaller copies.
+    /// mirrored copies would be culled, as their triangles are clockwise
+    fn instancing(&self) -> Option<Instancing> {
+        let supported = self.vertex_format == VertexFormat::Triangles
+            && self.transforms.iter().all(|t| t.determinant() >= 0.);
+
+        supported.then(|| Instancing {
+            base: self.data[0].clone(),
+            transforms: self.transforms.clone(),
+            keep_base: self.keep_base,
+        })
+    }
+
+    /// not when keeping the base, which is added to every iteration
+    fn replaces_groups(&self) -> bool { !self.keep_base }
+
+    fn next_iteration(&self, last_vertices: &[Vertex], _iteration: u32) -> Vec<Vertex> {
+        let group_size = self.vertex_format.group_size();
+        let base = if self.keep_base { self.data[0].as_slice() } else { &[] };
+
+        // one whole copy after the other
+        let copies = self.transforms.par_iter().flat_map(|transform| {
+            // keep triangles counterclockwise when mirrored
+            let mirrored = self.vertex_format == VertexFormat::Triangles && transform.determinant() < 0.;
+            last_vertices.par_chunks(group_size).flat_map_iter(move |group| {
+                (0..group.len()).map(move |i| {
+                    let vertex = group[if mirrored { group.len() - 1 - i } else { i }];
+                    Vertex::new(transform.apply(vertex.position), vertex.iteration + 1)
+                })
+            })
+        });
+
+        base.par_iter()
+            .copied()
+            .chain(copies)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::sierpinski_triangle::SierpinskiTriangle;
+
+    /// same triangles in the same order, up to rounding
+    #[test]
+    fn sierpinski_triangle_matches_hand_written() {
+        let mut self_similar = SelfSimilar::sierpinski_triangle();
+        let mut hand_written = SierpinskiTriangle::new();
+        for iteration in 0..6 {
+            let (self_similar, hand_written) = (self_similar.vertices(iteration), hand_written.vertices(iteration));
+            assert_eq!(self_similar.len(), hand_written.len());
+            for (a, b) in self_similar.iter().zip(hand_written) {
+                assert!((a.position - b.position).len() < 1e-12, "iteration {iteration}: {a:?} != {b:?}");
+                assert_eq!(a.iteration, b.iteration, "iteration {iteration}: {a:?} != {b:?}");
+            }
+        }
+    }
+}
@@ -1,30 +1,48 @@
 use rayon::prelude::*;
 
-use super::Curve;
-use crate::rendering::vertex::{Vertex, VertexFormat, vec2::Vec2};
+use super::{Curve, self_similar::SelfSimilar};
+use crate::vertex::{Vertex, VertexFormat, instanced::Instancing, vec2::DVec2};
 
 /// <https://en.wikipedia.org/wiki/Sierpi%C5%84ski_triangle>
 pub struct SierpinskiTriangle {
     data: Vec<Vec<Vertex>>
 }
 
-impl Curve for SierpinskiTriangle {
-    fn new() -> Self {
+impl SierpinskiTriangle {
+    pub fn new() -> Self {
         Self {
             data: vec![vec![
-                Vertex::new(Vec2::new( 0.00,  0.75), 0),
-                Vertex::new(Vec2::new(-0.75, -0.75), 0),
-                Vertex::new(Vec2::new( 0.75, -0.75), 0),
+                Vertex::new(DVec2::new( 0.00,  0.75), 0),
+                Vertex::new(DVec2::new(-0.75, -0.75), 0),
+                Vertex::new(DVec2::new( 0.75, -0.75), 0),
             ]],
         }
     }
+}
+
+impl Default for SierpinskiTriangle {
+    fn default() -> Self { Self::new() }
+}
 
+impl Curve for SierpinskiTriangle {
     fn vertex_format(&self) -> VertexFormat { VertexFormat::Triangles }
     fn default_iteration(&self) -> usize { 7 }
     
     fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
     fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
 
+    /// every triangle is replaced with 3 triangles
+    fn vertex_count(&self, iteration: usize) -> usize {
+        self.data[0].len().saturating_mul(3usize.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    /// every iteration is three half size copies of the last one
+    fn instancing(&self) -> Option<Instancing> {
+        SelfSimilar::sierpinski_triangle().instancing()
+    }
+
+    fn replaces_groups(&self) -> bool { true }
+
     fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
         last_vertices.par_chunks(3).map(|triangle| {
             let top = triangle[0].position;
@@ -54,3 +72,24 @@ impl Curve for SierpinskiTriangle {
         }).flatten().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// same triangles in the same order, up to rounding
+    #[test]
+    fn instanced_matches_vertices() {
+        let mut curve = SierpinskiTriangle::new();
+        let instancing = curve.instancing().unwrap();
+        for iteration in 0..6 {
+            let instanced = instancing.instanced(iteration).flatten();
+            let vertices = curve.vertices(iteration);
+            assert_eq!(instanced.len(), vertices.len());
+            for (a, b) in instanced.iter().zip(vertices) {
+                assert!((a.position - b.position).len() < 1e-12, "iteration {iteration}: {a:?} != {b:?}");
+                assert_eq!(a.iteration, b.iteration);
+            }
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use rayon::prelude::*;
+use std::f64::consts::PI;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use super::Curve;
+use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
+
+/// <https://en.wikipedia.org/wiki/Space-filling_curve>
+///
+/// a single continuous path through cells, where every iteration replaces each cell
+/// with smaller child cells. children depend on the orientation of their parent
+/// (which may be rotated or mirrored), so cells are kept alongside the vertices.
+pub struct SpaceFillingCurve {
+    data: Vec<Vec<Vertex>>,
+    /// one element for each iteration, like `data`
+    cells: Vec<Vec<Cell>>,
+    /// children of a cell, indexed by its kind
+    rules: Vec<Vec<Child>>,
+    path: Path,
+    default_iteration: usize,
+}
+
+/// part of the curve spanned by `u` and `v` from `origin`
+#[derive(Clone, Copy)]
+struct Cell {
+    origin: DVec2,
+    u: DVec2,
+    v: DVec2,
+    /// which rule to replace this cell with
+    kind: usize,
+}
+
+/// cell relative to its parent, in units of the parents `u` and `v`
+#[derive(Clone, Copy, Debug)]
+struct Child {
+    origin: DVec2,
+    u: DVec2,
+    v: DVec2,
+    kind: usize,
+}
+
+/// how the path runs through the cells
+#[derive(Clone, Copy, Debug)]
+enum Path {
+    /// from the center of each (square) cell to the center of the next one
+    CellCenters { closed: bool },
+    /// along `u` of each cell
+    CellLines,
+}
+
+impl Cell {
+    /// position of `local` in the coordinate system of this cell
+    fn point(&self, local: DVec2) -> DVec2 {
+        self.origin + self.vector(local)
+    }
+
+    fn vector(&self, local: DVec2) -> DVec2 {
+        (self.u * local.x) + (self.v * local.y)
+    }
+
+    fn child(&self, child: &Child) -> Self {
+        Self {
+            origin: self.point(child.origin),
+            u: self.vector(child.u),
+            v: self.vector(child.v),
+            kind: child.kind,
+        }
+    }
+
+    fn center(&self) -> DVec2 {
+        self.point(DVec2::new(0.5, 0.5))
+    }
+}
+
+/// shorthand for tables of children
+const fn child(origin: (f64, f64), u: (f64, f64), v: (f64, f64)) -> Child {
+    Child {
+        origin: DVec2::new(origin.0, origin.1),
+        u: DVec2::new(u.0, u.1),
+        v: DVec2::new(v.0, v.1),
+        kind: 0,
+    }
+}
+
+/// square cells entered at `origin` and left at `origin + u`, see
+/// <https://en.wikipedia.org/wiki/Hilbert_curve>
+const HILBERT_RULE: [Child; 4] = [
+    // mirrored along the diagonal
+    child((0.0, 0.0), (0.0,  0.5), ( 0.5, 0.0)),
+    child((0.0, 0.5), (0.5,  0.0), ( 0.0, 0.5)),
+    child((0.5, 0.5), (0.5,  0.0), ( 0.0, 0.5)),
+    // mirrored along the other diagonal
+    child((1.0, 0.5), (0.0, -0.5), (-0.5, 0.0)),
+];
+
+/// square cells entered at `origin` and left at `origin + u + v`, see
+/// <https://en.wikipedia.org/wiki/Peano_curve>
+const PEANO_RULE: [Child; 9] = {
+    const T: f64 = 1. / 3.;
+    [
+        child((0.,     0.    ), ( T, 0.), (0.,  T)),
+        child((T,      T     ), (-T, 0.), (0.,  T)),
+        child((0.,     2. * T), ( T, 0.), (0.,  T)),
+        child((T,      1.    ), ( T, 0.), (0., -T)),
+        child((2. * T, 2. * T), (-T, 0.), (0., -T)),
+        child((T,      T     ), ( T, 0.), (0., -T)),
+        child((2. * T, 0.    ), ( T, 0.), (0.,  T)),
+        child((1.,     T     ), (-T, 0.), (0.,  T)),
+        child((2. * T, 2. * T), ( T, 0.), (0.,  T)),
+    ]
+};
+
+/// four hilbert curves in a loop, see <https://en.wikipedia.org/wiki/Moore_curve>
+const MOORE_START: [Child; 4] = [
+    child((0.5, 0.0), (0.0,  0.5), (-0.5, 0.0)),
+    child((0.5, 0.5), (0.0,  0.5), (-0.5, 0.0)),
+    child((0.5, 1.0), (0.0, -0.5), ( 0.5, 0.0)),
+    child((0.5, 0.5), (0.0, -0.5), ( 0.5, 0.0)),
+];
+
+impl SpaceFillingCurve {
+    /// `root`: square (or line) the curve fills <br>
+    /// `start`: children of `root` forming the initial state
+    fn new(root: Cell, start: &[Child], rules: Vec<Vec<Child>>, path: Path, default_iteration: usize) -> Self {
+        let mut curve = Self {
+            cells: vec![start.iter().map(|c| root.child(c)).collect()],
+            rules,
+            path,
+            default_iteration,
+            data: vec![],
+        };
+        curve.data = vec![curve.vertices_of(&curve.cells[0], 0)];
+        curve
+    }
+
+    /// square the square-celled curves fill
+    fn square() -> Cell {
+        Cell {
+            origin: DVec2::new(-0.8, -0.8),
+            u: DVec2::new(1.6, 0.),
+            v: DVec2::new(0., 1.6),
+            kind: 0,
+        }
+    }
+
+    pub fn hilbert() -> Self {
+        Self::new(Self::square(), &HILBERT_RULE, vec![HILBERT_RULE.to_vec()], Path::CellCenters { closed: false }, 5)
+    }
+
+    pub fn peano() -> Self {
+        Self::new(Self::square(), &PEANO_RULE, vec![PEANO_RULE.to_vec()], Path::CellCenters { closed: false }, 3)
+    }
+
+    pub fn moore() -> Self {
+        Self::new(Self::square(), &MOORE_START, vec![HILBERT_RULE.to_vec()], Path::CellCenters { closed: true }, 5)
+    }
+
+    /// <https://en.wikipedia.org/wiki/Gosper_curve>, with cells being the lines
+    /// of the L-system `A -> A-B--B+A++AA+B-`, `B -> +A-BB--B-A++A+B` (60° angles)
+    pub fn gosper() -> Self {
+        let rules = ["A-B--B+A++AA+B-", "+A-BB--B-A++A+B"]
+            .map(|replacement| line_children(replacement, PI / 3.))
+            .to_vec();
+
+        let root = Cell {
+            // the curve bulges below the line from start to end
+            origin: DVec2::new(-0.7, 0.4),
+            u: DVec2::new(1.4, 0.),
+            v: DVec2::new(0., 1.4),
+            kind: 0,
+        };
+        let start = child((0., 0.), (1., 0.), (0., 1.));
+
+        Self::new(root, &[start], rules, Path::CellLines, 4)
+    }
+
+    /// number of children of every cell
+    fn branching(&self) -> usize {
+        self.rules[0].len()
+    }
+
+    fn subdivide(&self, cells: &[Cell]) -> Vec<Cell> {
+        cells.par_iter()
+            .flat_map_iter(|cell| self.rules[cell.kind].iter().map(|child| cell.child(child)))
+            .collect()
+    }
+
+    /// iteration in which the cells at `index` and `index + 1` were separated,
+    /// meaning their common ancestor is from the iteration before
+    fn separation(&self, index: usize, iteration: u32) -> u32 {
+        let branching = self.branching();
+        let (mut index, mut separation) = (index, iteration);
+        // last children share an ancestor with the next cell further up
+        while separation > 0 && index % branching == branching - 1 {
+            index /= branching;
+            separation -= 1;
+        }
+        separation
+    }
+
+    /// points where the path changes direction are tagged with the
+    /// iteration in which the cells on both sides were separated
+    fn vertices_of(&self, cells: &[Cell], iteration: u32) -> Vec<Vertex> {
+        match self.path {
+            Path::CellCenters { closed } => {
+                let lines = cells.par_windows(2)
+                    .enumerate()
+                    .flat_map_iter(|(i, pair)| {
+                        let tag = self.separation(i, iteration);
+                        [
+                            Vertex::new(pair[0].center(), tag),
+                            Vertex::new(pair[1].center(), tag),
+                        ]
+                    });
+
+                let closing = closed.then(|| [
+                    Vertex::new(cells[cells.len() - 1].center(), 0),
+                    Vertex::new(cells[0].center(), 0),
+                ]);
+
+                lines.chain(closing.into_par_iter().flatten()).collect()
+            },
+            Path::CellLines => {
+                let last = cells.len() - 1;
+                cells.par_iter()
+                    .enumerate()
+                    .flat_map_iter(|(i, cell)| {
+                        let start_tag = if i == 0 { 0 } else { self.separation(i - 1, iteration) };
+                        let end_tag = if i == last { 0 } else { self.separation(i, iteration) };
+                        [
+                            Vertex::new(cell.origin, start_tag),
+                            Vertex::new(cell.origin + cell.u, end_tag),
+                        ]
+                    })
+                    .collect()
+            },
+        }
+    }
+}
+
+/// children of a line from `(0, 0)` to `(1, 0)` drawn by a turtle following `replacement`,
+/// where `A` and `B` are lines of the respective kind and `+`/`-` turn by `angle`
+fn line_children(replacement: &str, angle: f64) -> Vec<Child> {
+    let mut lines = vec![];
+    let (mut position, mut direction) = (DVec2::new(0., 0.), DVec2::new(1., 0.));
+    for symbol in replacement.chars() {
+        match symbol {
+            'A' | 'B' => {
+                lines.push((position, direction, usize::from(symbol == 'B')));
+                position = position + direction;
+            },
+            '+' => direction = direction.rotate_ccw(angle),
+            '-' => direction = direction.rotate_cw(angle),
+            _ => (),
+        }
+    }
+
+    // rotate and scale all lines so that the path ends at (1, 0)
+    let end = position;
+    let to_local = |p: DVec2| DVec2::new(
+        p.x.mul_add(end.x,   p.y * end.y) / (end.len() * end.len()),
+        p.y.mul_add(end.x, -(p.x * end.y)) / (end.len() * end.len()),
+    );
+
+    lines.into_iter()
+        .map(|(position, direction, kind)| {
+            let u = to_local(direction);
+            Child { origin: to_local(position), u, v: -u.clockwise_orthogonal(), kind }
+        })
+        .collect()
+}
+
+impl Curve for SpaceFillingCurve {
+    fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
+    fn default_iteration(&self) -> usize { self.default_iteration }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    fn cache_key(&self) -> String {
+        format!("SpaceFillingCurve {:?} {:?}", self.rules, self.path)
+    }
+
+    fn vertex_count(&self, iteration: usize) -> usize {
+        let cells = self.cells[0].len()
+            .saturating_mul(self.branching().saturating_pow(iteration.try_into().unwrap_or(u32::MAX)));
+        let lines = match self.path {
+            Path::CellCenters { closed } => cells - usize::from(!closed),
+            Path::CellLines => cells,
+        };
+        lines.saturating_mul(2)
+    }
+
+    /// also keep the cells of the new iteration, which are evicted along with its vertices.
+    /// cells of the iterations before are subdivided first if they are missing
+    /// (because they were evicted or their vertices were loaded from the cache)
+    fn compute_iteration(&mut self, iteration: usize, cancel: &AtomicBool) -> bool {
+        // cells of the starting iteration are never evicted
+        let start = (0..iteration).rev()
+            .find(|&i| self.cells.get(i).is_some_and(|cells| !cells.is_empty()))
+            .unwrap();
+        for i in start + 1..iteration {
+            let cells = self.subdivide(&self.cells[i - 1]);
+            super::store_iteration(&mut self.cells, i, cells);
+        }
+        let cells = self.subdivide(&self.cells[iteration - 1]);
+        let vertices = self.vertices_of(&cells, iteration.try_into().unwrap());
+
+        if cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+
+        super::store_iteration(&mut self.cells, iteration, cells);
+        super::store_iteration(&mut self.data, iteration, vertices);
+        true
+    }
+
+    fn evict(&mut self, keep: &[usize]) {
+        super::evict_iterations(&mut self.cells, keep);
+        super::evict_iterations(&mut self.data, keep);
+    }
+
+    fn reset(&mut self) {
+        self.cells.truncate(1);
+        self.data.truncate(1);
+    }
+
+    fn next_iteration(&self, _last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        // vertices are not enough to know the orientation of each cell
+        let cells = self.subdivide(&self.cells[iteration as usize - 1]);
+        self.vertices_of(&cells, iteration)
+    }
+}
@@ -0,0 +1,97 @@
+//! computing an iteration depth-first, without intermediate iterations
+
+use super::Curve;
+use crate::vertex::Vertex;
+
+/// groups (lines/triangles/points) replaced at once. even, so that curves whose replacement
+/// depends on the parity of the index of a group (like a dragon curve folding its lines
+/// to alternating sides) see the same parity in every batch as in the whole iteration
+const BATCH_GROUPS: usize = 1 << 14;
+
+/// vertices of an iteration in chunks, see [`Curve::stream`]
+pub struct DepthFirst<'a, C: Curve + ?Sized> {
+    curve: &'a C,
+    iteration: usize,
+    /// batches of vertices of lower iterations still to be replaced,
+    /// with their iteration (next batch last)
+    stack: Vec<(usize, Vec<Vertex>)>,
+}
+
+impl<'a, C: Curve + ?Sized> DepthFirst<'a, C> {
+    /// `iteration` of `curve`, starting from the vertices of `start` (a lower iteration)
+    pub fn new(curve: &'a C, start: usize, vertices: &[Vertex], iteration: usize) -> Self {
+        let mut stream = Self { curve, iteration, stack: Vec::new() };
+        stream.push_batches(start, vertices);
+        stream
+    }
+
+    /// push `vertices` of `iteration` in batches, so that the first batch is next
+    fn push_batches(&mut self, iteration: usize, vertices: &[Vertex]) {
+        let batch_size = BATCH_GROUPS * self.curve.vertex_format().group_size();
+        self.stack.extend(vertices.chunks(batch_size).rev().map(|batch| (iteration, batch.to_vec())));
+    }
+}
+
+impl<C: Curve + ?Sized> Iterator for DepthFirst<'_, C> {
+    type Item = Vec<Vertex>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (iteration, batch) = self.stack.pop()?;
+            if iteration == self.iteration {
+                return Some(batch);
+            }
+            let next = self.curve.next_iteration(&batch, (iteration + 1).try_into().unwrap());
+            self.push_batches(iteration + 1, &next);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::curves::{Curve, koch_snowflake::KochSnowflake};
+    use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
+
+    /// heighway dragon folding to alternating sides by the index of each line
+    struct ParityDragon {
+        data: Vec<Vec<Vertex>>,
+    }
+
+    impl Curve for ParityDragon {
+        fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
+        fn default_iteration(&self) -> usize { 0 }
+
+        fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+        fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+        fn replaces_groups(&self) -> bool { true }
+
+        fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+            last_vertices.chunks(2).enumerate().flat_map(|(i, line)| {
+                let (a, b) = (line[0].position, line[1].position);
+                let side = if i % 2 == 0 { 1. } else { -1. };
+                let middle = a + ((b - a) / 2.) + ((b - a).clockwise_orthogonal() * (side / 2.));
+                [line[0], Vertex::new(middle, iteration), Vertex::new(middle, iteration), line[1]]
+            }).collect()
+        }
+    }
+
+    /// same as [`Curve::vertices`] and as replacing whole iterations one after the other
+    /// (which `vertices` does not do for these curves), with several batches on the way
+    fn assert_same_as_vertices(curve: &mut dyn Curve, iteration: usize) {
+        let streamed = curve.stream(iteration).unwrap().flatten().collect::<Vec<_>>();
+        let whole = (1..=iteration).fold(curve.data()[0].clone(), |vertices, i| {
+            curve.next_iteration(&vertices, i.try_into().unwrap())
+        });
+        assert!(whole.len() / curve.vertex_format().group_size() > 2 * super::BATCH_GROUPS);
+        assert!(streamed == whole);
+        assert!(streamed == *curve.vertices(iteration));
+    }
+
+    #[test]
+    fn stream_matches_vertices() {
+        assert_same_as_vertices(&mut KochSnowflake::new(), 8);
+        let line = [Vertex::new(DVec2::new(-0.5, 0.), 0), Vertex::new(DVec2::new(0.5, 0.), 0)];
+        assert_same_as_vertices(&mut ParityDragon { data: vec![line.to_vec()] }, 16);
+    }
+}
@@ -0,0 +1,46 @@
+use super::{Curve, heighway_dragon};
+use crate::vertex::{Vertex, VertexFormat, vec2::DVec2};
+
+/// <https://en.wikipedia.org/wiki/Dragon_curve#Twindragon>
+pub struct Twindragon {
+    data: Vec<Vec<Vertex>>
+}
+
+impl Twindragon {
+    pub fn new() -> Self {
+        Self {
+            // same line in both directions, so two heighway dragons
+            // grow from it (point symmetric to each other)
+            data: vec![vec![
+                Vertex::new(DVec2::new(-0.5, 0.), 0),
+                Vertex::new(DVec2::new( 0.5, 0.), 0),
+
+                Vertex::new(DVec2::new( 0.5, 0.), 0),
+                Vertex::new(DVec2::new(-0.5, 0.), 0),
+            ]],
+        }
+    }
+}
+
+impl Default for Twindragon {
+    fn default() -> Self { Self::new() }
+}
+
+impl Curve for Twindragon {
+    fn vertex_format(&self) -> VertexFormat { VertexFormat::Lines }
+    fn default_iteration(&self) -> usize { 12 }
+
+    fn     data(&    self) -> &    Vec<Vec<Vertex>> { &    self.data }
+    fn mut_data(&mut self) -> &mut Vec<Vec<Vertex>> { &mut self.data }
+
+    /// every line is replaced with 2 lines
+    fn vertex_count(&self, iteration: usize) -> usize {
+        self.data[0].len().saturating_mul(2usize.saturating_pow(iteration.try_into().unwrap_or(u32::MAX)))
+    }
+
+    fn replaces_groups(&self) -> bool { true }
+
+    fn next_iteration(&self, last_vertices: &[Vertex], iteration: u32) -> Vec<Vertex> {
+        heighway_dragon::next_iteration(last_vertices, iteration)
+    }
+}
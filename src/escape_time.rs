@@ -0,0 +1,224 @@
+//! fractals evaluated per pixel instead of being represented by vertices:
+//! points are iterated until they escape a radius around the origin,
+//! see <https://en.wikipedia.org/wiki/Mandelbrot_set#Computer_drawings>.
+//! evaluated on the gpu in `rendering/escape_time.wgsl` (keep them in sync),
+//! this is for rendering without a gpu
+
+use rayon::prelude::*;
+
+use crate::color;
+use crate::curves::parameter::{Parameter, Unit};
+use crate::raster::{self, Image};
+use crate::vertex::vec2::Vec2;
+
+/// which function to iterate
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    /// `z = z² + c` with `z` starting at 0 and `c` being the point
+    Mandelbrot,
+    /// `z = z² + c` with `z` starting at the point and `c` being a parameter
+    Julia,
+}
+
+pub struct EscapeTime {
+    kind: Kind,
+    parameters: Vec<Parameter>,
+}
+
+impl EscapeTime {
+    // indices of parameters
+    pub const MAX_ITERATIONS: usize = 0;
+    const ESCAPE_RADIUS: usize = 1;
+    const JULIA_REAL: usize = 2;
+    const JULIA_IMAGINARY: usize = 3;
+
+    fn new(kind: Kind) -> Self {
+        let mut parameters = vec![
+            // name, unit, default, min, max, step
+            Parameter::new("max-iterations", Unit::Integer, 256., 16., 4096., 16.),
+            Parameter::new("escape-radius",  Unit::None, 8.,   2.,  256.,  2.),
+        ];
+        if kind == Kind::Julia {
+            parameters.extend([
+                Parameter::new("julia-real",      Unit::None, -0.8,   -2., 2., 0.01),
+                Parameter::new("julia-imaginary", Unit::None,  0.156, -2., 2., 0.01),
+            ]);
+        }
+
+        Self { kind, parameters }
+    }
+
+    /// <https://en.wikipedia.org/wiki/Mandelbrot_set>
+    pub fn mandelbrot() -> Self {
+        Self::new(Kind::Mandelbrot)
+    }
+
+    /// <https://en.wikipedia.org/wiki/Julia_set>
+    pub fn julia() -> Self {
+        Self::new(Kind::Julia)
+    }
+
+    pub const fn kind(&self) -> Kind {
+        self.kind
+    }
+
+    pub fn parameters(&self) -> &[Parameter] {
+        &self.parameters
+    }
+
+    /// returns `true` if value was actually changed
+    pub fn set_parameter(&mut self, index: usize, value: f32) -> bool {
+        self.parameters[index].set(value)
+    }
+
+    /// `!increment == decrement` <br>
+    /// returns `true` if value was actually changed
+    pub fn step_parameter(&mut self, index: usize, increment: bool) -> bool {
+        self.parameters[index].step(increment)
+    }
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    pub fn max_iterations(&self) -> u32 {
+        self.parameters[Self::MAX_ITERATIONS].value().round() as u32
+    }
+
+    pub fn escape_radius(&self) -> f32 {
+        self.parameters[Self::ESCAPE_RADIUS].value()
+    }
+
+    /// `c` of the julia set, `None` for the mandelbrot set
+    pub fn julia_constant(&self) -> Option<Vec2> {
+        (self.kind == Kind::Julia).then(|| Vec2::new(
+            self.parameters[Self::JULIA_REAL].value(),
+            self.parameters[Self::JULIA_IMAGINARY].value(),
+        ))
+    }
+
+    /// point of the complex plane in the center of the window
+    pub const fn center(&self) -> Vec2 {
+        match self.kind {
+            Kind::Mandelbrot => Vec2::new(-0.75, 0.),
+            Kind::Julia => Vec2::new(0., 0.),
+        }
+    }
+
+    /// distance on the complex plane from the center to the edges of the window
+    pub const fn scale(&self) -> f32 {
+        match self.kind {
+            Kind::Mandelbrot => 1.3,
+            Kind::Julia => 1.7,
+        }
+    }
+
+    /// smooth number of iterations until `position` (in range [-1, 1] like vertices) escapes,
+    /// `None` if it does not escape within the max. number of iterations
+    #[allow(clippy::cast_precision_loss)]
+    pub fn evaluate(&self, position: Vec2) -> Option<f32> {
+        let point = (position * self.scale()) + self.center();
+        let (mut z, c) = match self.julia_constant() {
+            None => (Vec2::new(0., 0.), point),
+            Some(constant) => (point, constant),
+        };
+
+        let radius_squared = self.escape_radius() * self.escape_radius();
+        for iteration in 0..self.max_iterations() {
+            // z² + c, with z and c being complex numbers
+            z = Vec2::new(
+                z.x.mul_add(z.x, -(z.y * z.y)) + c.x,
+                (2. * z.x).mul_add(z.y, c.y),
+            );
+
+            let len_squared = z.x.mul_add(z.x, z.y * z.y);
+            if len_squared > radius_squared {
+                // fraction of an iteration from how far the radius was exceeded, see
+                // <https://en.wikipedia.org/wiki/Plotting_algorithms_for_the_Mandelbrot_set#Continuous_(smooth)_coloring>
+                return Some(iteration as f32 + 1. - (len_squared.ln() / radius_squared.ln()).log2());
+            }
+        }
+
+        None
+    }
+
+    /// evaluate every pixel on the cpu, keeping the aspect ratio like the window does
+    #[allow(clippy::cast_precision_loss)]
+    pub fn rasterize(&self, width: u32, height: u32) -> Image {
+        log::debug!("evaluating {width}x{height} pixels");
+
+        let (width_px, height_px) = (width as usize, height as usize);
+        let position_scale = raster::position_scale(width, height);
+        let max_iterations = self.max_iterations() as f32;
+
+        let mut pixels = vec![0; width_px * height_px * 3];
+        pixels.par_chunks_mut(width_px * 3)
+            .enumerate()
+            .for_each(|(row, row_pixels)| {
+                let y = (row as f32 + 0.5) / height as f32;
+                for (column, pixel) in row_pixels.chunks_exact_mut(3).enumerate() {
+                    let x = (column as f32 + 0.5) / width as f32;
+                    // inverse of the pixel coordinates in raster::rasterize
+                    let position = Vec2::new(x.mul_add(2., -1.), y.mul_add(-2., 1.)) / position_scale;
+
+                    let color = color::escape_time_color(self.evaluate(position), max_iterations, 0.);
+                    pixel.copy_from_slice(&color::to_srgb8(color));
+                }
+            });
+
+        Image { width, height, pixels }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// position (like vertices) of `point` of the complex plane
+    fn position(escape_time: &EscapeTime, point: Vec2) -> Vec2 {
+        (point - escape_time.center()) / escape_time.scale()
+    }
+
+    #[test]
+    fn mandelbrot_inside_and_outside() {
+        let mandelbrot = EscapeTime::mandelbrot();
+        // fixed point, cycle of length 2 and inside the main cardioid
+        for inside in [Vec2::new(0., 0.), Vec2::new(-1., 0.), Vec2::new(-0.1, 0.3)] {
+            assert_eq!(mandelbrot.evaluate(position(&mandelbrot, inside)), None, "{inside:?}");
+        }
+        for outside in [Vec2::new(1., 0.), Vec2::new(0.5, 0.5), Vec2::new(-2.1, 0.)] {
+            assert!(mandelbrot.evaluate(position(&mandelbrot, outside)).is_some(), "{outside:?}");
+        }
+    }
+
+    #[test]
+    fn smooth_across_escape_counts() {
+        let mandelbrot = EscapeTime::mandelbrot();
+        let values = (0..=1000)
+            .map(|i| Vec2::new(0.5, 0.) + Vec2::new(1., 0.) * (i as f32 / 1000.))
+            .map(|point| mandelbrot.evaluate(position(&mandelbrot, point)).unwrap())
+            .collect::<Vec<_>>();
+
+        // escape after a different number of whole iterations along the way
+        assert!(values.first().unwrap().floor() > values.last().unwrap().floor());
+        for pair in values.windows(2) {
+            assert!((pair[0] - pair[1]).abs() < 0.05, "{pair:?}");
+        }
+    }
+
+    #[test]
+    fn julia_constant_changes_output() {
+        let mut julia = EscapeTime::julia();
+        let positions = (0..100).map(|i| Vec2::new(i as f32 / 100., 0.3)).collect::<Vec<_>>();
+        let evaluate = |julia: &EscapeTime| positions.iter().map(|&p| julia.evaluate(p)).collect::<Vec<_>>();
+
+        let before = evaluate(&julia);
+        assert!(julia.set_parameter(EscapeTime::JULIA_REAL, 0.3));
+        assert_ne!(evaluate(&julia), before);
+        assert_eq!(EscapeTime::mandelbrot().julia_constant(), None);
+    }
+
+    #[test]
+    fn rasterize_size() {
+        let image = EscapeTime::julia().rasterize(7, 5);
+        assert_eq!((image.width, image.height), (7, 5));
+        assert_eq!(image.pixels.len(), 7 * 5 * 3);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{fs::File, io::{self, BufReader, BufWriter, Write}, path::Path};
+
+use fractals::mesh::{Counts, Mesh};
+
+/// print the header of the mesh file at `path`
+pub fn info(path: &Path) {
+    let (mesh, counts) = match File::open(path).and_then(|file| Mesh::read_header(&mut BufReader::new(file))) {
+        Ok(header) => header,
+        Err(e) => exit_with(path, &e),
+    };
+    print(|out| write_header(out, &mesh, &counts));
+}
+
+/// print the header and all vertices (and indices) of the mesh file at `path`
+pub fn dump(path: &Path) {
+    let mesh = match Mesh::load(path) {
+        Ok(mesh) => mesh,
+        Err(e) => exit_with(path, &e),
+    };
+    let counts = Counts {
+        header_bytes: 0,
+        vertices: mesh.vertices.len() as u64,
+        indices: mesh.indices.as_ref().map_or(0, Vec::len) as u64,
+    };
+    print(|out| {
+        write_header(out, &mesh, &counts)?;
+        write_arrays(out, &mesh)
+    });
+}
+
+/// write to stdout with `write`, exiting on errors
+fn print(write: impl FnOnce(&mut BufWriter<io::StdoutLock<'static>>) -> io::Result<()>) {
+    let mut out = BufWriter::new(io::stdout().lock());
+    if let Err(e) = write(&mut out).and_then(|()| out.flush()) {
+        // e.g. piped into `head`, which is fine
+        if e.kind() != io::ErrorKind::BrokenPipe {
+            log::error!("could not print mesh: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+fn write_header(out: &mut impl Write, mesh: &Mesh, counts: &Counts) -> io::Result<()> {
+    writeln!(out, "curve: {}", mesh.curve)?;
+    for (name, value) in &mesh.parameters {
+        writeln!(out, "parameter: {name} = {value}")?;
+    }
+    writeln!(out, "iteration: {}", mesh.iteration + 1)?;
+    writeln!(out, "vertex format: {:?}", mesh.vertex_format)?;
+    writeln!(out, "origin: {} {}", mesh.origin.x, mesh.origin.y)?;
+    writeln!(out, "vertices: {}", counts.vertices)?;
+    match &mesh.indices {
+        Some(_) => writeln!(out, "indices: {}", counts.indices),
+        None => writeln!(out, "indices: none"),
+    }
+}
+
+/// one line per vertex (`v x y iteration`, with positions relative to the origin)
+/// and one per line/triangle/point of indices (`i a b c`)
+fn write_arrays(out: &mut impl Write, mesh: &Mesh) -> io::Result<()> {
+    for vertex in &mesh.vertices {
+        writeln!(out, "v {} {} {}", vertex.position.x, vertex.position.y, vertex.iteration)?;
+    }
+    for group in mesh.indices.iter().flat_map(|indices| indices.chunks(mesh.vertex_format.group_size())) {
+        let group = group.iter().map(ToString::to_string).collect::<Vec<_>>();
+        writeln!(out, "i {}", group.join(" "))?;
+    }
+    Ok(())
+}
+
+fn exit_with(path: &Path, e: &io::Error) -> ! {
+    log::error!("could not read {}: {e}", path.display());
+    std::process::exit(1);
+}
@@ -0,0 +1,13 @@
+//! fractal geometry as vertices, ready to be rendered as triangles
+//! (or evaluated per pixel for escape-time fractals).
+//! rendering it in a window requires the `gui` feature (enabled by default)
+
+pub mod color;
+pub mod curves;
+pub mod escape_time;
+pub mod mesh;
+pub mod raster;
+pub mod svg;
+pub mod vertex;
+#[cfg(feature = "gui")]
+pub mod rendering;
@@ -1,43 +1,323 @@
-mod curves;
-mod rendering;
 mod benchmark;
+mod cache;
+mod inspect;
+mod render;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use fractals::{curves::{self, Fractal, cache::Cache}, rendering, vertex::{estimate::{Estimate, MAX_INDICES}, lod}};
 
 #[derive(Parser)]
 #[command(version, about)]
 struct Args {
     #[command(subcommand)]
     command: Option<Command>,
+    /// Load an additional fractal from a definition file.
+    /// Can be used multiple times, files in the config directory
+    /// (e.g. `~/.config/fractals/*.toml`) are always loaded.
+    #[arg(short, long, value_name = "FILE")]
+    definition: Vec<std::path::PathBuf>,
+    /// Refuse to compute iterations that are estimated to use more memory than this
+    #[arg(long, value_name = "MIB", default_value_t = 4096, global = true)]
+    memory_budget: usize,
+    /// Number of recently shown iterations to keep in memory,
+    /// so that going back to them does not compute them again
+    #[arg(long, value_name = "COUNT", default_value_t = 3)]
+    cached_iterations: usize,
+    /// Delete the least recently used iterations cached on disk
+    /// when all of them together get larger than this
+    #[arg(long, value_name = "MIB", default_value_t = 4096, global = true)]
+    cache_size: u64,
+    /// Neither load iterations from the cache on disk nor store them there
+    #[arg(long, global = true)]
+    no_cache: bool,
 }
 
 #[derive(Subcommand)]
 enum Command {
     /// Run CPU/memory benchmark by computing the triangles necessary
-    /// to represent a given fractal iteration (without rendering it)
+    /// to represent a given fractal iteration (without rendering it).
+    /// Escape-time fractals are evaluated for a 1920x1080 image instead.
+    /// Iterations cached on disk are neither loaded nor stored.
     Bench {
-        /// Type of fractal to use
-        #[arg(
-            short, long, value_enum,
-            default_value_t = curves::Curves::KochSnowflake,
-        )]
-        r#type: curves::Curves,
-        /// Iteration to compute, 1 meaning the initial state.
-        /// Be careful when increasing this, you will eventually run out of memory.
-        #[arg(
-            short, long,
-            default_value_t = 10,
-            value_parser = clap::value_parser!(i8).range(1..)
-        )]
-        iteration: i8,
+        #[command(flatten)]
+        curve: CurveArgs,
+        /// Merge vertices closer than this distance when indexing (e.g. `1e-9`),
+        /// so that differences from floating point rounding do not prevent sharing them
+        #[arg(long, value_name = "TOLERANCE")]
+        weld: Option<f64>,
     },
+    /// Render a given fractal iteration to a PNG image on the CPU (without a window or GPU)
+    Render {
+        #[command(flatten)]
+        curve: CurveArgs,
+        /// Merge vertices closer than this distance when indexing (e.g. `1e-9`),
+        /// so that differences from floating point rounding do not prevent sharing them
+        #[arg(long, value_name = "TOLERANCE")]
+        weld: Option<f64>,
+        /// Merge and drop details smaller than a pixel of the image before rendering
+        #[arg(long)]
+        lod: bool,
+        /// Width of the image in pixels
+        #[arg(long, default_value_t = 1920, value_parser = clap::value_parser!(u32).range(1..))]
+        width: u32,
+        /// Height of the image in pixels
+        #[arg(long, default_value_t = 1080, value_parser = clap::value_parser!(u32).range(1..))]
+        height: u32,
+        /// Path of the image to write
+        #[arg(short, long, value_name = "FILE", default_value = "fractal.png")]
+        output: std::path::PathBuf,
+    },
+    /// Export a given fractal iteration as SVG image
+    Svg {
+        #[command(flatten)]
+        curve: CurveArgs,
+        /// Merge and drop details smaller than a pixel of an image of this size
+        /// (smaller side), to keep the file small
+        #[arg(long, value_name = "PIXELS", value_parser = clap::value_parser!(u32).range(1..))]
+        lod: Option<u32>,
+        /// Path of the image to write
+        #[arg(short, long, value_name = "FILE", default_value = "fractal.svg")]
+        output: std::path::PathBuf,
+    },
+    /// Save a fractal iteration as binary mesh file, or inspect such files
+    Mesh {
+        #[command(subcommand)]
+        action: MeshAction,
+    },
+    /// Manage computed iterations cached on disk (e.g. in `~/.cache/fractals`),
+    /// so that large iterations do not have to be computed again.
+    /// Only iterations with at least a million vertices are cached.
+    Cache {
+        #[command(subcommand)]
+        action: CacheAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum MeshAction {
+    /// Save a given fractal iteration as binary mesh file
+    Save {
+        #[command(flatten)]
+        curve: CurveArgs,
+        /// Represent lines and points as triangles, like for rendering
+        #[arg(long)]
+        triangles: bool,
+        /// Store every distinct vertex once, with indices referencing them
+        #[arg(long)]
+        index: bool,
+        /// Path of the file to write
+        #[arg(short, long, value_name = "FILE", default_value = "fractal.mesh")]
+        output: std::path::PathBuf,
+    },
+    /// Print the header of a mesh file (fractal, parameters, iteration, number of vertices...)
+    Info {
+        file: std::path::PathBuf,
+    },
+    /// Print the header and all vertices and indices of a mesh file as text
+    Dump {
+        file: std::path::PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum CacheAction {
+    /// List cached iterations with their fractal, parameters and size
+    List,
+    /// Delete all cached iterations
+    Clear,
+}
+
+/// options to select a fractal iteration, shared by subcommands
+#[derive(clap::Args)]
+struct CurveArgs {
+    /// Type of fractal to use
+    #[arg(
+        short, long, value_enum,
+        default_value_t = curves::Curves::KochSnowflake,
+    )]
+    r#type: curves::Curves,
+    /// Iteration to compute, 1 meaning the initial state.
+    /// Memory usage increases exponentially with every iteration increase.
+    /// Escape-time fractals use the `max-iterations` parameter instead.
+    #[arg(
+        short, long,
+        default_value_t = 10,
+        value_parser = clap::value_parser!(i8).range(1..)
+    )]
+    iteration: i8,
+    /// Use fractal from a definition file instead of a built-in type
+    #[arg(short, long, value_name = "FILE", conflicts_with = "type")]
+    definition: Option<std::path::PathBuf>,
+    /// Set a parameter of the fractal, e.g. `left-angle=0.25`.
+    /// Can be used multiple times, unknown parameters will list the available ones.
+    #[arg(short, long = "param", value_name = "NAME=VALUE", value_parser = parse_parameter)]
+    params: Vec<(String, f32)>,
+    /// Compute the iteration even if it exceeds the memory budget
+    #[arg(long)]
+    force: bool,
+}
+
+impl CurveArgs {
+    /// load the definition file if given, exits if it cannot be loaded
+    fn definition(&self) -> Option<curves::definition::Definition> {
+        let path = self.definition.as_ref()?;
+        match curves::definition::Definition::load(path) {
+            Ok(definition) => Some(definition),
+            Err(e) => {
+                log::error!("{e}");
+                std::process::exit(1);
+            },
+        }
+    }
+
+    /// create fractal instance with parameters applied, together with
+    /// the type of the fractal or name of the definition.
+    /// exits on unknown parameters
+    fn fractal(&self) -> (String, Fractal) {
+        let (name, mut fractal) = match self.definition() {
+            None => (self.r#type.to_possible_value().unwrap().get_name().to_owned(), self.r#type.new_instance()),
+            Some(definition) => (definition.name.clone(), Fractal::Curve(std::sync::Arc::new(definition).new_instance())),
+        };
+
+        for (name, value) in &self.params {
+            let Some(index) = fractal.parameter_index(name) else {
+                let available = fractal.parameters().iter()
+                    .map(|p| format!("{} (default {}, range [{}, {}])", p.name, p.default, p.min, p.max))
+                    .collect::<Vec<_>>();
+                if available.is_empty() {
+                    log::error!("unknown parameter '{name}', this fractal has no parameters");
+                } else {
+                    log::error!("unknown parameter '{name}', available parameters:\n{}", available.join("\n"));
+                }
+                std::process::exit(1);
+            };
+
+            fractal.set_parameter(index, *value);
+            log::info!("set {}", fractal.parameters()[index]);
+        }
+
+        (name, fractal)
+    }
+
+    fn iteration(&self) -> usize {
+        self.iteration.try_into().unwrap()
+    }
+
+    /// log `estimate` of the iteration and exit if it exceeds
+    /// `memory_budget` (in bytes), unless forced, or cannot be indexed
+    fn check_limits(&self, estimate: Estimate, memory_budget: usize) {
+        log::info!("estimated {estimate}");
+
+        if estimate.indices() > MAX_INDICES {
+            log::error!("estimated to exceed the maximum of {MAX_INDICES} indices");
+            std::process::exit(1);
+        }
+
+        if estimate.memory_bytes > memory_budget {
+            let budget = fractals::vertex::estimate::mib(memory_budget);
+            if !self.force {
+                log::error!("estimated memory usage exceeds the memory budget of {budget} MiB, use --force to run anyway");
+                std::process::exit(1);
+            }
+            log::warn!("estimated memory usage exceeds the memory budget of {budget} MiB");
+        }
+    }
+}
+
+fn parse_parameter(arg: &str) -> Result<(String, f32), String> {
+    let (name, value) = arg.split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got '{arg}'"))?;
+    let parsed = value.parse::<f32>()
+        .map_err(|e| format!("invalid value '{value}': {e}"))?;
+    // e.g. nan, inf or too large for f32
+    if !parsed.is_finite() {
+        return Err(format!("invalid value '{value}': expected a finite number"));
+    }
+    Ok((name.to_owned(), parsed))
 }
 
 fn main() {
-    match Args::parse().command {
-        Some(Command::Bench { iteration, r#type })
-            => benchmark::run(iteration.try_into().unwrap(), r#type),
-        None => rendering::run(),
+    let args = Args::parse();
+    let memory_budget = args.memory_budget.saturating_mul(1 << 20);
+    let cache = Cache::default_directory()
+        .map(|directory| Cache::new(directory, args.cache_size.saturating_mul(1 << 20)));
+    // not for benchmarks, which would measure loading instead of computing
+    let no_cache = args.no_cache;
+    let enable_cache = |cache: Option<Cache>| if let Some(cache) = cache.filter(|_| !no_cache) {
+        cache.enable();
+    };
+    match args.command {
+        Some(Command::Bench { curve, weld }) => {
+            log_init("debug");
+            match curve.fractal().1 {
+                Fractal::Curve(curve_instance) => {
+                    curve.check_limits(curve_instance.instanced_estimate(curve.iteration() - 1), memory_budget);
+                    benchmark::run(curve.iteration(), curve_instance, weld);
+                },
+                Fractal::EscapeTime(escape_time) => benchmark::run_escape_time(&escape_time),
+            }
+        },
+        Some(Command::Render { curve, weld, lod, width, height, output }) => {
+            log_init("info");
+            enable_cache(cache);
+            match curve.fractal().1 {
+                Fractal::Curve(curve_instance) => {
+                    curve.check_limits(curve_instance.estimate(curve.iteration() - 1), memory_budget);
+                    render::run(curve.iteration(), curve_instance, weld, lod, (width, height), &output);
+                },
+                Fractal::EscapeTime(escape_time) => render::run_escape_time(&escape_time, (width, height), &output),
+            }
+        },
+        Some(Command::Svg { curve, lod, output }) => {
+            log_init("info");
+            enable_cache(cache);
+            let (_, Fractal::Curve(mut curve_instance)) = curve.fractal() else {
+                log::error!("escape-time fractals are evaluated per pixel and cannot be exported as svg");
+                std::process::exit(1);
+            };
+            curve.check_limits(curve_instance.estimate(curve.iteration() - 1), memory_budget);
+            if let Err(e) = fractals::svg::export(&mut *curve_instance, curve.iteration() - 1, lod.map(|pixels| lod::pixel_size(pixels, pixels, 1.)), &output) {
+                log::error!("could not write {}: {e}", output.display());
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Mesh { action: MeshAction::Save { curve, triangles, index, output } }) => {
+            log_init("info");
+            enable_cache(cache);
+            let (name, Fractal::Curve(mut curve_instance)) = curve.fractal() else {
+                log::error!("escape-time fractals are evaluated per pixel and cannot be saved as mesh");
+                std::process::exit(1);
+            };
+            curve.check_limits(curve_instance.estimate(curve.iteration() - 1), memory_budget);
+            if let Err(e) = fractals::mesh::export(name, &mut *curve_instance, curve.iteration() - 1, triangles, index, &output) {
+                log::error!("could not write {}: {e}", output.display());
+                std::process::exit(1);
+            }
+        },
+        Some(Command::Mesh { action: MeshAction::Info { file } }) => {
+            log_init("info");
+            inspect::info(&file);
+        },
+        Some(Command::Mesh { action: MeshAction::Dump { file } }) => {
+            log_init("info");
+            inspect::dump(&file);
+        },
+        Some(Command::Cache { action }) => {
+            log_init("info");
+            let Some(cache) = cache else {
+                log::error!("could not find the cache directory");
+                std::process::exit(1);
+            };
+            match action {
+                CacheAction::List => cache::list(&cache),
+                CacheAction::Clear => cache::clear(&cache),
+            }
+        },
+        None => {
+            log_init("info");
+            enable_cache(cache);
+            rendering::run(&args.definition, memory_budget, args.cached_iterations);
+        },
     }
 }
 
@@ -0,0 +1,459 @@
+//! binary file format for fractal geometry, to pass it between machines and tools. <br>
+//! all numbers are little-endian. vertices are stored like in the vertex buffer
+//! ([`BufferVertex`], relative to an origin), and the header is padded so they start
+//! at a multiple of 4 bytes, so they can be used without conversion (on little-endian machines):
+//!
+//! | content                                          | type                  |
+//! |--------------------------------------------------|-----------------------|
+//! | [`MAGIC`]                                        | 8 bytes               |
+//! | [`FORMAT_VERSION`]                               | u32                   |
+//! | [`VertexFormat`]: lines 0, triangles 1, points 2 | u32                   |
+//! | iteration (0 meaning initial state)              | u32                   |
+//! | whether there are indices (0 or 1)               | u32                   |
+//! | origin x, y                                      | f64, f64              |
+//! | number of vertices, number of indices            | u64, u64              |
+//! | curve type                                       | string                |
+//! | number of parameters                             | u32                   |
+//! | each parameter: name, value                      | string, f32           |
+//! | zeros up to a multiple of 4 bytes                |                       |
+//! | vertices: position x, y, iteration               | f32, f32, u32 (each)  |
+//! | indices (if there are any)                       | u32 (each)            |
+//!
+//! strings are their length in bytes (u32) followed by UTF-8.
+
+use std::{fs::File, io::{self, BufReader, BufWriter, Read, Write}, mem::size_of, path::Path};
+
+use crate::curves::{Curve, parameter::Parameter};
+use crate::vertex::{self, BufferVertex, POINT_SIZE, Vertex, VertexFormat, tiles::Bounds, vec2::{DVec2, Vec2}};
+
+/// at the start of every file
+pub const MAGIC: &[u8; 8] = b"FRACMESH";
+/// increases whenever the layout changes, files of other versions are not loaded
+pub const FORMAT_VERSION: u32 = 1;
+/// strings longer than this are not from a valid file
+const MAX_STRING_BYTES: u32 = 1 << 16;
+/// vertices and indices are read in parts of this size, so that a corrupted
+/// header fails when the data ends instead of allocating too much up front
+const READ_CHUNK_BYTES: usize = 1 << 20;
+
+/// fractal geometry with what it was computed from, see the [module](self) for the file layout
+#[derive(Clone, PartialEq, Debug)]
+pub struct Mesh {
+    /// type of the curve, e.g. `koch-snowflake` or the name of a definition
+    pub curve: String,
+    /// names and values
+    pub parameters: Vec<(String, f32)>,
+    /// 0 meaning initial state
+    pub iteration: u32,
+    pub vertex_format: VertexFormat,
+    /// `vertices` are relative to this
+    pub origin: DVec2,
+    pub vertices: Vec<BufferVertex>,
+    /// into `vertices`, each group of [`VertexFormat::group_size`] forming a line/triangle/point.
+    /// `None` if `vertices` are already in that order
+    pub indices: Option<Vec<u32>>,
+}
+
+impl Mesh {
+    /// `vertices` (and `indices`, as returned by [`crate::vertex::index`]) of `iteration`,
+    /// stored relative to the center of their bounds to keep them precise
+    pub fn new(
+        curve: String,
+        parameters: &[Parameter],
+        iteration: usize,
+        vertex_format: VertexFormat,
+        vertices: &[Vertex],
+        indices: Option<Vec<u32>>,
+    ) -> Self {
+        let bounds = vertices.iter()
+            .fold(Bounds::EMPTY, |bounds, vertex| bounds.including(vertex.position));
+        let origin = if vertices.is_empty() { DVec2::default() } else { (bounds.min + bounds.max) / 2. };
+
+        Self {
+            curve,
+            parameters: parameters.iter().map(|p| (p.name.to_owned(), p.value())).collect(),
+            iteration: iteration.try_into().unwrap(),
+            vertex_format,
+            origin,
+            vertices: vertex::to_buffer(vertices, origin),
+            indices,
+        }
+    }
+
+    /// with double precision, in the order of `indices` (if there are any)
+    pub fn vertices(&self) -> Vec<Vertex> {
+        let vertex = |v: &BufferVertex| Vertex::new(
+            self.origin + DVec2::new(v.position.x.into(), v.position.y.into()),
+            v.iteration,
+        );
+        match &self.indices {
+            Some(indices) => indices.iter().map(|&i| vertex(&self.vertices[i as usize])).collect(),
+            None => self.vertices.iter().map(vertex).collect(),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+
+    /// like [`Self::read`], but also checks that the size of the file
+    /// matches the header before reading vertices and indices
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let file_bytes = file.metadata()?.len();
+        let mut input = BufReader::new(file);
+        let (mut mesh, counts) = Self::read_header(&mut input)?;
+        if counts.file_bytes() != file_bytes {
+            return Err(invalid(&format!("expected {} bytes for {} vertices and {} indices, but the file has {file_bytes}", counts.file_bytes(), counts.vertices, counts.indices)));
+        }
+        mesh.read_arrays(&mut input, &counts)?;
+        Ok(mesh)
+    }
+
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        let mut header = Vec::new();
+        header.extend(MAGIC);
+        for value in [FORMAT_VERSION, format_to_u32(self.vertex_format), self.iteration, self.indices.is_some().into()] {
+            header.extend(value.to_le_bytes());
+        }
+        header.extend(self.origin.x.to_le_bytes());
+        header.extend(self.origin.y.to_le_bytes());
+        header.extend((self.vertices.len() as u64).to_le_bytes());
+        header.extend((self.indices.as_ref().map_or(0, Vec::len) as u64).to_le_bytes());
+        write_str(&mut header, &self.curve)?;
+        header.extend(to_u32(self.parameters.len())?.to_le_bytes());
+        for (name, value) in &self.parameters {
+            write_str(&mut header, name)?;
+            header.extend(value.to_le_bytes());
+        }
+        header.resize(header.len().next_multiple_of(4), 0);
+        out.write_all(&header)?;
+
+        if cfg!(target_endian = "little") {
+            out.write_all(bytemuck::cast_slice(&self.vertices))?;
+            out.write_all(bytemuck::cast_slice(self.indices.as_deref().unwrap_or_default()))
+        } else {
+            for vertex in &self.vertices {
+                out.write_all(&vertex.position.x.to_le_bytes())?;
+                out.write_all(&vertex.position.y.to_le_bytes())?;
+                out.write_all(&vertex.iteration.to_le_bytes())?;
+            }
+            for index in self.indices.iter().flatten() {
+                out.write_all(&index.to_le_bytes())?;
+            }
+            Ok(())
+        }
+    }
+
+    /// fails with [`io::ErrorKind::InvalidData`] if it is not a valid file of this version
+    pub fn read(input: &mut impl Read) -> io::Result<Self> {
+        let (mut mesh, counts) = Self::read_header(input)?;
+        mesh.read_arrays(input, &counts)?;
+        Ok(mesh)
+    }
+
+    /// only the header of a file, so `vertices` and `indices` are empty
+    /// (`indices` is `Some` if the file has indices)
+    pub fn read_header(input: &mut impl Read) -> io::Result<(Self, Counts)> {
+        let mut input = Reader { input, bytes_read: 0 };
+
+        if input.bytes(MAGIC.len())? != MAGIC {
+            return Err(invalid("not a mesh file"));
+        }
+        let version = input.u32()?;
+        if version != FORMAT_VERSION {
+            return Err(invalid(&format!("unsupported format version {version}, expected {FORMAT_VERSION}")));
+        }
+        let vertex_format = match input.u32()? {
+            0 => VertexFormat::Lines,
+            1 => VertexFormat::Triangles,
+            2 => VertexFormat::Points,
+            other => return Err(invalid(&format!("unknown vertex format {other}"))),
+        };
+        let iteration = input.u32()?;
+        let indexed = match input.u32()? {
+            0 => false,
+            1 => true,
+            other => return Err(invalid(&format!("invalid value {other} for whether there are indices"))),
+        };
+        let origin = DVec2::new(f64::from_bits(input.u64()?), f64::from_bits(input.u64()?));
+        let vertices = input.u64()?;
+        let indices = input.u64()?;
+        let curve = input.string()?;
+        let parameters = (0..input.u32()?)
+            .map(|_| Ok((input.string()?, f32::from_bits(input.u32()?))))
+            .collect::<io::Result<Vec<_>>>()?;
+        input.bytes(input.bytes_read.next_multiple_of(4) - input.bytes_read)?;
+
+        if !indexed && indices != 0 {
+            return Err(invalid("indices without being indexed"));
+        }
+        if indices % vertex_format.group_size() as u64 != 0 {
+            return Err(invalid("indices do not form whole groups"));
+        }
+
+        let mesh = Self {
+            curve,
+            parameters,
+            iteration,
+            vertex_format,
+            origin,
+            vertices: Vec::new(),
+            indices: indexed.then(Vec::new),
+        };
+        Ok((mesh, Counts { header_bytes: input.bytes_read as u64, vertices, indices }))
+    }
+
+    /// vertices and indices following the header
+    fn read_arrays(&mut self, input: &mut impl Read, counts: &Counts) -> io::Result<()> {
+        // read directly into the final memory, converting afterwards if necessary
+        self.vertices = read_vec(input, counts.vertices)?;
+        if let Some(indices) = &mut self.indices {
+            *indices = read_vec(input, counts.indices)?;
+        }
+        if cfg!(target_endian = "big") {
+            for vertex in &mut self.vertices {
+                vertex.position = Vec2::new(
+                    f32::from_bits(u32::from_le(vertex.position.x.to_bits())),
+                    f32::from_bits(u32::from_le(vertex.position.y.to_bits())),
+                );
+                vertex.iteration = u32::from_le(vertex.iteration);
+            }
+            for index in self.indices.iter_mut().flatten() {
+                *index = u32::from_le(*index);
+            }
+        }
+
+        if input.read(&mut [0])? != 0 {
+            return Err(invalid("unexpected data after the indices"));
+        }
+        if self.indices.iter().flatten().any(|&i| i as usize >= self.vertices.len()) {
+            return Err(invalid("index out of range of the vertices"));
+        }
+        Ok(())
+    }
+}
+
+/// write `iteration` of `curve_instance` (computing it if necessary) to a file at `path`. <br>
+/// `curve`: type of the curve, see [`Mesh::curve`] <br>
+/// `triangles`: represent lines and points as triangles, like for rendering <br>
+/// `index`: store every distinct vertex once, with indices (see [`vertex::index`])
+pub fn export(curve: String, curve_instance: &mut dyn Curve, iteration: usize, triangles: bool, index: bool, path: &Path) -> io::Result<()> {
+    let stroke = curve_instance.stroke();
+    let parameters = curve_instance.parameters().to_vec();
+    let mut vertex_format = curve_instance.vertex_format();
+    // same vertices, but only the ones of this iteration are computed
+    let flattened;
+    let vertices = match curve_instance.instancing() {
+        Some(instancing) => {
+            flattened = instancing.instanced(iteration).flatten();
+            &flattened
+        },
+        None => curve_instance.vertices(iteration),
+    };
+    let as_triangles;
+    let vertices = match vertex_format {
+        VertexFormat::Lines if triangles => {
+            as_triangles = vertex::lines_as_triangles(vertices, &stroke);
+            &as_triangles
+        },
+        VertexFormat::Points if triangles => {
+            as_triangles = vertex::points_as_triangles(vertices, POINT_SIZE);
+            &as_triangles
+        },
+        _ => vertices,
+    };
+    if triangles {
+        vertex_format = VertexFormat::Triangles;
+    }
+
+    let mesh = if index {
+        let (unique_vertices, indices) = vertex::index(vertices);
+        Mesh::new(curve, &parameters, iteration, vertex_format, &unique_vertices, Some(indices))
+    } else {
+        Mesh::new(curve, &parameters, iteration, vertex_format, vertices, None)
+    };
+    mesh.save(path)?;
+
+    log::info!("exported iteration {} to {}", iteration + 1, path.display());
+    Ok(())
+}
+
+/// sizes of the parts of a file
+#[derive(Clone, Copy, Debug)]
+pub struct Counts {
+    /// including padding
+    pub header_bytes: u64,
+    pub vertices: u64,
+    pub indices: u64,
+}
+
+impl Counts {
+    /// of the whole file
+    pub const fn file_bytes(&self) -> u64 {
+        self.header_bytes
+            .saturating_add(self.vertices.saturating_mul(size_of::<BufferVertex>() as u64))
+            .saturating_add(self.indices.saturating_mul(size_of::<u32>() as u64))
+    }
+}
+
+/// counts the bytes read, to know the padding of the header
+struct Reader<'a, R: Read> {
+    input: &'a mut R,
+    bytes_read: usize,
+}
+
+impl<R: Read> Reader<'_, R> {
+    fn bytes(&mut self, n: usize) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0; n];
+        self.input.read_exact(&mut bytes).map_err(|e| truncated(e, "the header"))?;
+        self.bytes_read += n;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.bytes(4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> io::Result<u64> {
+        self.bytes(8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()?;
+        if len > MAX_STRING_BYTES {
+            return Err(invalid("string too long"));
+        }
+        String::from_utf8(self.bytes(len as usize)?).map_err(|_| invalid("string is not UTF-8"))
+    }
+}
+
+/// `count` elements, growing the result only as far as the data goes
+fn read_vec<T: bytemuck::Pod>(input: &mut impl Read, count: u64) -> io::Result<Vec<T>> {
+    let chunk = (READ_CHUNK_BYTES / size_of::<T>()) as u64;
+    let mut elements = Vec::new();
+    let mut remaining = count;
+    while remaining > 0 {
+        let start = elements.len();
+        elements.resize(start + to_usize(remaining.min(chunk))?, T::zeroed());
+        input.read_exact(bytemuck::cast_slice_mut(&mut elements[start..]))
+            .map_err(|e| truncated(e, "the vertices and indices"))?;
+        remaining -= remaining.min(chunk);
+    }
+    Ok(elements)
+}
+
+fn write_str(out: &mut Vec<u8>, string: &str) -> io::Result<()> {
+    let len = to_u32(string.len())?;
+    if len > MAX_STRING_BYTES {
+        return Err(invalid("string too long"));
+    }
+    out.extend(len.to_le_bytes());
+    out.extend(string.as_bytes());
+    Ok(())
+}
+
+const fn format_to_u32(vertex_format: VertexFormat) -> u32 {
+    match vertex_format {
+        VertexFormat::Lines => 0,
+        VertexFormat::Triangles => 1,
+        VertexFormat::Points => 2,
+    }
+}
+
+fn to_u32(len: usize) -> io::Result<u32> {
+    len.try_into().map_err(|_| invalid("too many elements"))
+}
+
+fn to_usize(count: u64) -> io::Result<usize> {
+    count.try_into().map_err(|_| invalid("too many elements for this machine"))
+}
+
+/// [`io::ErrorKind::InvalidData`] if the data ends within `part`, otherwise `e` unchanged
+fn truncated(e: io::Error, part: &str) -> io::Error {
+    if e.kind() == io::ErrorKind::UnexpectedEof {
+        invalid(&format!("file ends within {part}"))
+    } else {
+        e
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::parameter::Unit;
+
+    fn mesh(indices: bool) -> Mesh {
+        let vertices = [(0., 0.), (1., 0.), (0., 1.), (1., 1.)]
+            .map(|(x, y)| Vertex::new(DVec2::new(x, y), 1));
+        let parameters = [Parameter::new("angle", Unit::FactorOfPi, 0.25, 0., 1., 0.01)];
+        if indices {
+            Mesh::new("test".to_owned(), &parameters, 2, VertexFormat::Triangles, &vertices, Some(vec![0, 1, 2, 2, 1, 3]))
+        } else {
+            Mesh::new("test".to_owned(), &parameters, 2, VertexFormat::Lines, &vertices, None)
+        }
+    }
+
+    fn bytes(mesh: &Mesh) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        mesh.write(&mut bytes).unwrap();
+        bytes
+    }
+
+    fn read_error(bytes: &[u8]) -> io::ErrorKind {
+        Mesh::read(&mut &bytes[..]).unwrap_err().kind()
+    }
+
+    #[test]
+    fn round_trip() {
+        for indices in [false, true] {
+            let mesh = mesh(indices);
+            let bytes = bytes(&mesh);
+            assert_eq!(bytes.len() % 4, 0);
+            assert_eq!(Mesh::read(&mut &bytes[..]).unwrap(), mesh);
+
+            let (header, counts) = Mesh::read_header(&mut &bytes[..]).unwrap();
+            assert_eq!(header.curve, "test");
+            assert_eq!(counts.file_bytes(), bytes.len() as u64);
+        }
+    }
+
+    #[test]
+    fn truncated_is_invalid() {
+        let bytes = bytes(&mesh(true));
+        for len in [0, 4, MAGIC.len() + 4, 40, bytes.len() - 4, bytes.len() - 1] {
+            assert_eq!(read_error(&bytes[..len]), io::ErrorKind::InvalidData, "{len} bytes");
+        }
+
+        let mut longer = bytes;
+        longer.push(0);
+        assert_eq!(read_error(&longer), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn corrupted_header_is_invalid() {
+        let bytes = bytes(&mesh(true));
+        // see the module for the layout: magic, 4 times u32, origin, counts
+        let corrupt = |offset: usize, value: &[u8]| {
+            let mut bytes = bytes.clone();
+            bytes[offset..offset + value.len()].copy_from_slice(value);
+            read_error(&bytes)
+        };
+        assert_eq!(corrupt(0, b"NOTAMESH"), io::ErrorKind::InvalidData);
+        assert_eq!(corrupt(8, &(FORMAT_VERSION + 1).to_le_bytes()), io::ErrorKind::InvalidData);
+        assert_eq!(corrupt(12, &3_u32.to_le_bytes()), io::ErrorKind::InvalidData);
+        assert_eq!(corrupt(20, &2_u32.to_le_bytes()), io::ErrorKind::InvalidData);
+        // too many vertices and indices, without allocating them
+        assert_eq!(corrupt(40, &u64::MAX.to_le_bytes()), io::ErrorKind::InvalidData);
+        assert_eq!(corrupt(48, &(u64::MAX / 3 * 3).to_le_bytes()), io::ErrorKind::InvalidData);
+        // index out of range
+        let last = bytes.len() - 4;
+        assert_eq!(corrupt(last, &4_u32.to_le_bytes()), io::ErrorKind::InvalidData);
+    }
+}
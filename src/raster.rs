@@ -0,0 +1,138 @@
+//! rendering indexed triangles to an image on the cpu,
+//! looking like the window would (without animation)
+
+use rayon::prelude::*;
+
+use crate::color::{self, Rgb};
+use crate::vertex::{Vertex, vec2::Vec2};
+
+/// rows of pixels rendered together on one thread
+const BAND_HEIGHT: usize = 32;
+
+/// 8 bit rgb, row by row from the top left
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+/// render triangles (as returned by [`crate::vertex::index`]) on a black background,
+/// keeping the aspect ratio of the content like the window does
+pub fn rasterize(vertices: &[Vertex], indices: &[u32], width: u32, height: u32) -> Image {
+    log::debug!("rasterizing {} triangles", indices.len() / 3);
+
+    let (width_px, height_px) = (width as usize, height as usize);
+    let mut pixels = vec![0; width_px * height_px * 3];
+
+    let position_scale = position_scale(width, height);
+
+    let max_iteration = vertices.par_iter()
+        .map(|v| v.iteration)
+        .max()
+        .unwrap_or_default();
+
+    // in pixel coordinates, y pointing down
+    #[allow(clippy::cast_precision_loss)]
+    let positions = vertices.par_iter()
+        .map(|v| {
+            let scaled = v.position.to_f32() * position_scale;
+            Vec2::new(
+                (scaled.x + 1.) / 2. * width as f32,
+                (1. - scaled.y) / 2. * height as f32,
+            )
+        })
+        .collect::<Vec<_>>();
+    let colors = vertices.par_iter()
+        .map(|v| color::vertex_color(v, max_iteration, 0.))
+        .collect::<Vec<_>>();
+
+    // triangles overlapping each band, in drawing order
+    let band_count = height_px.div_ceil(BAND_HEIGHT);
+    let mut bands = vec![vec![]; band_count];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+
+        // same culling as the render pipeline: counterclockwise triangles are front faces,
+        // which are clockwise here because the y-axis is flipped
+        if cross(b - a, c - a) >= 0. {
+            continue;
+        }
+
+        let Some(rows) = pixel_range(a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y), height_px) else {
+            continue;
+        };
+        for band in &mut bands[rows.start / BAND_HEIGHT ..= (rows.end - 1) / BAND_HEIGHT] {
+            band.push(triangle);
+        }
+    }
+
+    pixels.par_chunks_mut(BAND_HEIGHT * width_px * 3)
+        .zip(bands)
+        .enumerate()
+        .for_each(|(band_index, (band_pixels, triangles))| {
+            let first_row = band_index * BAND_HEIGHT;
+            let band_rows = first_row .. first_row + band_pixels.len() / (width_px * 3);
+
+            for triangle in triangles {
+                let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+                let [color_a, color_b, color_c] = [0, 1, 2].map(|i| colors[triangle[i] as usize]);
+                let area = cross(b - a, c - a);
+
+                let (Some(rows), Some(columns)) = (
+                    pixel_range(a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y), height_px),
+                    pixel_range(a.x.min(b.x).min(c.x), a.x.max(b.x).max(c.x), width_px),
+                ) else { continue };
+
+                for row in rows.start.max(band_rows.start) .. rows.end.min(band_rows.end) {
+                    for column in columns.clone() {
+                        #[allow(clippy::cast_precision_loss)]
+                        let center = Vec2::new(column as f32 + 0.5, row as f32 + 0.5);
+
+                        // barycentric coordinates, all positive inside the triangle
+                        let weight_a = cross(c - b, center - b) / area;
+                        let weight_b = cross(a - c, center - c) / area;
+                        let weight_c = cross(b - a, center - a) / area;
+                        if weight_a < 0. || weight_b < 0. || weight_c < 0. {
+                            continue;
+                        }
+
+                        // interpolated like the output of the vertex shader
+                        let color: Rgb = [0, 1, 2].map(|i| weight_c.mul_add(
+                            color_c[i],
+                            weight_a.mul_add(color_a[i], weight_b * color_b[i]),
+                        ));
+
+                        let offset = ((row - first_row) * width_px + column) * 3;
+                        band_pixels[offset .. offset + 3].copy_from_slice(&color::to_srgb8(color));
+                    }
+                }
+            }
+        });
+
+    Image { width, height, pixels }
+}
+
+/// factor for positions to keep the aspect ratio of the content
+/// independent of the image size, same as in `rendering::state::State::resize`
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn position_scale(width: u32, height: u32) -> Vec2 {
+    let ratio = width as f32 / height as f32;
+    match ratio {
+        x if x > 1. => Vec2::new(1. / x, 1.),
+        x if x < 1. => Vec2::new(1., x),
+        _ => Vec2::new(1., 1.),
+    }
+}
+
+/// z-component of the cross product
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x.mul_add(b.y, -(a.y * b.x))
+}
+
+/// pixels with their center in [`min`, `max`], `None` if there are none
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss, clippy::cast_precision_loss)]
+fn pixel_range(min: f32, max: f32, len: usize) -> Option<std::ops::Range<usize>> {
+    let start = (min - 0.5).ceil().max(0.);
+    let end = ((max - 0.5).floor() + 1.).min(len as f32);
+    (start < end).then_some(start as usize .. end as usize)
+}
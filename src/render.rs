@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use fractals::vertex::{self, POINT_SIZE, VertexFormat};
+use fractals::{curves::Curve, escape_time::EscapeTime, raster};
+
+/// render `iteration` of `curve_instance` to a png image at `output` without a gpu. <br>
+/// `weld`: tolerance for merging nearly coincident vertices, see [`vertex::weld`] <br>
+/// `lod`: whether to drop details smaller than a pixel of the image, see [`vertex::lod`]
+#[allow(clippy::needless_pass_by_value)]
+pub fn run(iteration: usize, mut curve_instance: Box<dyn Curve>, weld: Option<f64>, lod: bool, (width, height): (u32, u32), output: &Path) {
+    let vertex_format = curve_instance.vertex_format();
+    let stroke = curve_instance.stroke();
+
+    // same vertices, but only the ones of this iteration are computed
+    let flattened;
+    let unformatted_vertices = match curve_instance.instancing() {
+        Some(instancing) => {
+            flattened = instancing.instanced(iteration - 1).flatten();
+            &flattened
+        },
+        None => curve_instance.vertices(iteration - 1),
+    };
+    let simplified;
+    let unformatted_vertices = if lod {
+        let pixel_size = vertex::lod::pixel_size(width, height, 1.);
+        simplified = vertex::lod::simplify(unformatted_vertices, vertex_format, pixel_size).0;
+        &simplified
+    } else {
+        unformatted_vertices
+    };
+    let raw_vertices = match vertex_format {
+        VertexFormat::Lines => &vertex::lines_as_triangles(unformatted_vertices, &stroke),
+        VertexFormat::Triangles => unformatted_vertices,
+        VertexFormat::Points => &vertex::points_as_triangles(unformatted_vertices, POINT_SIZE),
+    };
+    let (vertices, indices) = match weld {
+        Some(tolerance) => {
+            let (vertices, indices, _) = vertex::index_welded(raw_vertices, tolerance);
+            (vertices, indices)
+        },
+        None => vertex::index(raw_vertices),
+    };
+
+    save(raster::rasterize(&vertices, &indices, width, height), output);
+}
+
+/// render `escape_time` to a png image at `output` without a gpu
+pub fn run_escape_time(escape_time: &EscapeTime, (width, height): (u32, u32), output: &Path) {
+    save(escape_time.rasterize(width, height), output);
+}
+
+fn save(image: raster::Image, output: &Path) {
+    let (width, height) = (image.width, image.height);
+    let buffer = image::RgbImage::from_raw(width, height, image.pixels).unwrap();
+    if let Err(e) = buffer.save_with_format(output, image::ImageFormat::Png) {
+        log::error!("could not write {}: {e}", output.display());
+        std::process::exit(1);
+    }
+    log::info!("wrote {width}x{height} image to {}", output.display());
+}
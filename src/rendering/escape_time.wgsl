@@ -0,0 +1,123 @@
+// same as in shader.wgsl
+struct Globals {
+    max_iteration: u32,
+    animation_value: f32,
+    position_scale: vec2<f32>,
+    // relative to origin
+    camera_offset: vec2<f32>,
+    // position the vertices are relative to
+    origin: vec2<f32>,
+    camera_zoom: f32,
+};
+@group(0) @binding(0)
+var<uniform> globals: Globals;
+
+// matches rendering::state::EscapeTimeBufferContent struct in rust code
+struct EscapeTime {
+    center: vec2<f32>,
+    julia_constant: vec2<f32>,
+    scale: f32,
+    escape_radius: f32,
+    max_iterations: u32,
+    // 0 for the mandelbrot set
+    julia: u32,
+};
+@group(0) @binding(1)
+var<uniform> escape_time: EscapeTime;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    // in the coordinate system of the vertices of curves
+    @location(0) curve_position: vec2<f32>,
+};
+
+// iterations after which the hue repeats
+const HUE_CYCLE: f32 = 64;
+
+// all input and output values in range [0, 1], same as in shader.wgsl
+fn hsl_to_rgb(h: f32, s: f32, l: f32) -> vec3<f32> {
+    let a = s * min(l, 1 - l);
+    return vec3(
+        hsl_to_rgb_helper(0, a, h, s, l),
+        hsl_to_rgb_helper(8, a, h, s, l),
+        hsl_to_rgb_helper(4, a, h, s, l),
+    );
+}
+fn hsl_to_rgb_helper(n: f32, a: f32, h: f32, s: f32, l: f32) -> f32 {
+    let k = (n + (h * 12)) % 12;
+    return l - (a * clamp(min(k - 3, 9 - k), -1, 1));
+}
+
+// value in range [0, 1]
+fn scale_to(value: f32, min: f32, max: f32) -> f32 {
+    return (value * (max - min)) + min;
+}
+
+@vertex
+fn vertex(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // one triangle covering the whole window
+    let position = vec2(f32(index & 1) * 4 - 1, f32(index >> 1) * 4 - 1);
+
+    var out: VertexOutput;
+    out.position = vec4(position, 0, 1);
+    // inverse of maintaining aspect ratio and camera in shader.wgsl
+    out.curve_position = position / globals.position_scale / globals.camera_zoom + globals.camera_offset + globals.origin;
+    return out;
+}
+
+// ported to src/escape_time.rs and src/color.rs for rendering on the cpu, keep them in sync
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    ////////// evaluation //////////
+
+    let point = in.curve_position * escape_time.scale + escape_time.center;
+    var z = vec2<f32>(0, 0);
+    var c = point;
+    if escape_time.julia != 0 {
+        z = point;
+        c = escape_time.julia_constant;
+    }
+
+    let radius_squared = escape_time.escape_radius * escape_time.escape_radius;
+    // smooth number of iterations until escaping
+    var iterations = 0.;
+    var escaped = false;
+    for (var i = 0u; i < escape_time.max_iterations; i++) {
+        // z² + c, with z and c being complex numbers
+        z = vec2(z.x * z.x - z.y * z.y + c.x, 2 * z.x * z.y + c.y);
+
+        let len_squared = dot(z, z);
+        if len_squared > radius_squared {
+            // fraction of an iteration from how far the radius was exceeded
+            iterations = f32(i) + 1 - log2(log(len_squared) / log(radius_squared));
+            escaped = true;
+            break;
+        }
+    }
+
+    ////////// color //////////
+
+    if !escaped {
+        return vec4(0, 0, 0, 1);
+    }
+
+    // in range [0, 1]
+    let scaled_iterations = clamp(iterations / f32(escape_time.max_iterations), 0, 1);
+
+    // cycling with the number of iterations
+    var h = iterations / HUE_CYCLE;
+    // rotating like the curves do
+    h += -globals.animation_value + 1;
+    // same offset as the lowest iteration of curves
+    h += 0.3;
+    // ensure range [0, 1]
+    h %= 1;
+
+    // saturation
+    let s = scale_to(scaled_iterations, 0.8, 1);
+
+    // luminance, brighter closer to the set
+    let l = scale_to(sqrt(scaled_iterations), 0, 0.55);
+
+    return vec4(hsl_to_rgb(h, s, l), 1);
+}
@@ -1,8 +1,15 @@
-pub mod vertex;
 pub mod state;
 pub mod windowing;
+pub mod worker;
 
-pub fn run() {
-    super::log_init("info");
-    windowing::run_app();
+use std::path::PathBuf;
+
+use crate::curves::definition::Definition;
+
+/// `definition_paths`: additional fractals to load
+/// from definition files (besides the config directory) <br>
+/// `memory_budget`: in bytes, iterations estimated to exceed it are not computed <br>
+/// `cached_iterations`: number of recently shown iterations to keep computed
+pub fn run(definition_paths: &[PathBuf], memory_budget: usize, cached_iterations: usize) {
+    windowing::run_app(Definition::load_all(definition_paths), memory_budget, cached_iterations);
 }
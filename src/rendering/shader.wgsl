@@ -2,16 +2,29 @@ struct Globals {
     max_iteration: u32,
     animation_value: f32,
     position_scale: vec2<f32>,
+    // relative to origin
+    camera_offset: vec2<f32>,
+    // position the vertices are relative to
+    origin: vec2<f32>,
+    camera_zoom: f32,
 };
 @group(0) @binding(0)
 var<uniform> globals: Globals;
 
-// matches renderer::vertex::Vertex struct in rust code
+// matches renderer::vertex::BufferVertex struct in rust code
 struct VertexInput {
     @location(0) position: vec2<f32>,
     @location(1) iteration: u32,
 };
 
+// matches renderer::vertex::instanced::BufferInstance struct in rust code
+struct InstanceInput {
+    @location(2) x_axis: vec2<f32>,
+    @location(3) y_axis: vec2<f32>,
+    @location(4) offset: vec2<f32>,
+    @location(5) iteration: u32,
+};
+
 struct VertexOutput {
     @builtin(position) position: vec4<f32>,
     @location(1) color: vec3<f32>,
@@ -40,18 +53,22 @@ fn scale_to(value: f32, min: f32, max: f32) -> f32 {
 }
 
 @vertex
-fn vertex(in: VertexInput) -> VertexOutput {
-    let pos = in.position;
+fn vertex(in: VertexInput, instance: InstanceInput) -> VertexOutput {
+    // copy of the vertex this instance stands for, relative to the origin
+    let pos = instance.x_axis * in.position.x + instance.y_axis * in.position.y + instance.offset;
+    // precise enough for colors
+    let absolute_pos = pos + globals.origin;
+    let iteration = in.iteration + instance.iteration;
 
     // in range [0, 1]
-    var scaled_iteration = f32(in.iteration) / f32(globals.max_iteration);
+    var scaled_iteration = f32(iteration) / f32(globals.max_iteration);
     // overwrite value if there was division by 0
     if globals.max_iteration == 0 {
         scaled_iteration = 1;
     }
 
     // in range [-PI, PI], 0 means pointing up (positive y)
-    let angle = atan2(pos.x, pos.y);
+    let angle = atan2(absolute_pos.x, absolute_pos.y);
     // in range [0, 1]
     let scaled_angle = (angle + PI) / (2 * PI);
 
@@ -63,11 +80,13 @@ fn vertex(in: VertexInput) -> VertexOutput {
 
     ////////// position //////////
 
-    // to maintain aspect ratio
-    let scaled_position = pos * globals.position_scale;
+    // camera, then maintain aspect ratio
+    let camera_position = (pos - globals.camera_offset) * globals.camera_zoom;
+    let scaled_position = camera_position * globals.position_scale;
     out.position = vec4(scaled_position, 0, 1);
 
     ////////// color //////////
+    // ported to src/color.rs for rendering on the cpu, keep them in sync
     // compute color here, as moving any of this logic
     // to the fragment shader changes the visuals
     // in an unwanted way because of interpolation
@@ -1,25 +1,39 @@
 use std::sync::Arc;
-use rayon::prelude::*;
 use wgpu::util::DeviceExt;
 use winit::{
-    dpi::PhysicalSize,
-    event_loop::{ActiveEventLoop, ControlFlow},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event_loop::{ActiveEventLoop, ControlFlow, EventLoopProxy},
     window::Window
 };
 
-use super::vertex::{self, Vertex, VertexFormat, vec2::Vec2};
-use crate::curves::{Curve, Curves};
+use super::worker::{Job, Mesh, UserEvent};
+use crate::vertex::{self, BufferVertex, estimate::{Estimate, MAX_INDICES, mib}, instanced::{self, BufferInstance}, lod, tiles::Bounds, vec2::{DVec2, Vec2}};
+use crate::curves::{Curve, Fractal, Registry};
+use crate::escape_time::EscapeTime;
+use crate::svg;
 
 /// how many seconds an animation cycle should take.
 /// must be < 60.
 const SECS_PER_ANIMATION_CYCLE: f32 = 5.;
 /// value of state.animate at startup 
 const INITIAL_ANIMATE: bool = true;
-/// for representing lines as triangles
-const LINE_WIDTH: f32 = 0.005;
+/// range of camera zoom, too much zoom shows the limited precision of vertices
+const MIN_ZOOM: f64 = 0.5;
+const MAX_ZOOM: f64 = 1e10;
+/// escape-time fractals are evaluated with single precision on the gpu
+const MAX_ESCAPE_TIME_ZOOM: f64 = 1e5;
+/// max. distance of the camera to the origin of the buffers (relative to the visible area)
+/// before uploading them again relative to the camera, as the single precision of buffers
+/// would be visibly imprecise otherwise
+const MAX_ORIGIN_DISTANCE: f64 = 512.;
+/// how much smaller pixels have to get (by zooming in or enlarging the window)
+/// than the ones the current mesh was simplified for to compute it again with more details
+const LOD_REFINE_FACTOR: f64 = 2.;
+/// how far the camera moves on key press, relative to the visible area
+const PAN_STEP: f32 = 0.1;
 
 #[repr(C)]
-#[derive(Default, Clone, Copy, Debug, bytemuck::Zeroable, bytemuck::Pod)]
+#[derive(Clone, Copy, Debug, bytemuck::Zeroable, bytemuck::Pod)]
 // note that types were chosen to correspond to the few available options in WGSL
 struct UniformBufferContent {
     /// highest iteration value present in the current vertices
@@ -29,6 +43,55 @@ struct UniformBufferContent {
     /// x, y in range (0.0, 1.0] to maintain the same aspect ratio
     /// of the window content independent of the aspect ratio of the window
     position_scale: Vec2,
+    /// position of the curve in the center of the window, relative to `origin`
+    camera_offset: Vec2,
+    /// position the vertices in the buffers are relative to
+    origin: Vec2,
+    /// factor to scale the curve with around `camera_offset`
+    camera_zoom: f32,
+    /// struct size needs to be a multiple of its alignment (8) in WGSL
+    _padding: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, bytemuck::Zeroable, bytemuck::Pod)]
+/// parameters of the escape-time fractal to render, see [`EscapeTime`]
+struct EscapeTimeBufferContent {
+    center: Vec2,
+    /// unused for the mandelbrot set
+    julia_constant: Vec2,
+    scale: f32,
+    escape_radius: f32,
+    max_iterations: u32,
+    /// 0 for the mandelbrot set, 1 for julia sets (as there are no booleans in uniform buffers)
+    julia: u32,
+}
+
+impl EscapeTimeBufferContent {
+    fn new(escape_time: &EscapeTime) -> Self {
+        Self {
+            center: escape_time.center(),
+            julia_constant: escape_time.julia_constant().unwrap_or_default(),
+            scale: escape_time.scale(),
+            escape_radius: escape_time.escape_radius(),
+            max_iterations: escape_time.max_iterations(),
+            julia: escape_time.julia_constant().is_some().into(),
+        }
+    }
+}
+
+impl Default for UniformBufferContent {
+    fn default() -> Self {
+        Self {
+            max_iteration: 0,
+            animation_value: 0.,
+            position_scale: Vec2::new(1., 1.),
+            camera_offset: Vec2::new(0., 0.),
+            origin: Vec2::new(0., 0.),
+            camera_zoom: 1.,
+            _padding: 0,
+        }
+    }
 }
 
 pub struct State {
@@ -38,19 +101,46 @@ pub struct State {
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     uniform_buffer_content: UniformBufferContent, 
-    vertex_buffer: Option<wgpu::Buffer>,
-    index_buffer: Option<wgpu::Buffer>, 
+    /// one for each chunk of the mesh
+    vertex_buffers: Vec<wgpu::Buffer>,
+    /// one for each chunk of the mesh
+    index_buffers: Vec<wgpu::Buffer>,
+    /// with the number of instances in each, as there might be too many for one buffer
+    instance_buffers: Vec<(wgpu::Buffer, u32)>,
     uniform_buffer: wgpu::Buffer, 
+    escape_time_buffer: wgpu::Buffer,
     uniform_buffer_bind_group: wgpu::BindGroup,
     render_pipeline: wgpu::RenderPipeline,
-    num_indices: u32,
+    escape_time_pipeline: wgpu::RenderPipeline,
+    /// ranges of the indices to draw, only the tiles in view (see [`Mesh::tiles`])
+    visible_indices: Vec<std::ops::Range<u32>>,
+    /// last computed mesh, to upload again when the origin changes
+    mesh: Option<Mesh>,
+    /// position the vertices in the buffers are relative to, close to the camera
+    origin: DVec2,
+    /// position of the curve in the center of the window
+    camera_offset: DVec2,
+    /// factor to scale the curve with around `camera_offset`
+    camera_zoom: f64,
 
     // winit things
     pub window: Arc<Window>,
     pub size: PhysicalSize<u32>,
 
-    pub curve: Curves,
-    pub curve_instance: Box<dyn Curve>,
+    pub curve: Registry,
+    /// `None` while a curve is moved to the worker thread of `job`
+    fractal: Option<Fractal>,
+    /// computation of buffer contents in progress
+    job: Option<Job>,
+    /// to send events from the worker thread
+    proxy: EventLoopProxy<UserEvent>,
+    /// in bytes, iterations estimated to exceed it are not computed
+    memory_budget: usize,
+    /// how many of `recent_iterations` to keep computed
+    cached_iterations: usize,
+    /// iterations of the current curve shown most recently (current one last),
+    /// the only ones kept computed besides the starting iteration, see [`Curve::evict`]
+    recent_iterations: Vec<usize>,
     /// dont render when surface is not configured yet
     pub surface_configured: bool,
     /// whether to animate the colors of the curve, which means
@@ -59,13 +149,20 @@ pub struct State {
     /// offset for animation value in uniform buffer to guarantee smooth
     /// starting/stopping of animation from where it last was
     pub animation_value_offset: f32,
-    /// iteration of curve
+    /// iteration of curve currently in the buffers
     pub iteration: usize,
+    /// index of curve parameter to adjust
+    pub selected_parameter: usize,
+    /// last known position of the cursor in the window
+    pub cursor_position: Option<PhysicalPosition<f64>>,
+    /// whether the camera is moved with the cursor
+    pub dragging: bool,
 }
 
 impl State {
+    /// `cached_iterations`: number of recently shown iterations to keep computed
     #[allow(clippy::too_many_lines)]
-    pub async fn new(window: Arc<Window>) -> Self {
+    pub async fn new(window: Arc<Window>, curve: Registry, proxy: EventLoopProxy<UserEvent>, memory_budget: usize, cached_iterations: usize) -> Self {
         let size = window.inner_size();
 
         // to create surface and adapter
@@ -121,43 +218,61 @@ impl State {
         };
 
         // set some initial default values
-        let curve = Curves::default();
-        let curve_instance = curve.new_instance();
+        let fractal = Some(curve.new_instance());
+        let job = None;
         let animate = INITIAL_ANIMATE;
         let animation_value_offset = 0.;
-        let iteration = curve_instance.default_iteration();
-        let num_indices = Default::default();
+        // nothing in the buffers yet
+        let iteration = 0;
+        let selected_parameter = 0;
+        let visible_indices = Vec::new();
         let uniform_buffer_content = UniformBufferContent::default();
-        let vertex_buffer = None;
-        let index_buffer = None;
+        let vertex_buffers = Vec::new();
+        let index_buffers = Vec::new();
+        let instance_buffers = Vec::new();
 
         let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
             label: Some("uniform buffer"),
             usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             contents: bytemuck::cast_slice(&[uniform_buffer_content]),
         });
-        
+
+        let escape_time_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("escape time buffer"),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            contents: bytemuck::cast_slice(&[EscapeTimeBufferContent::default()]),
+        });
+
+        let uniform_buffer_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+
+        // shared by both pipelines, the one for curves just ignores the escape time buffer
         let uniform_buffer_bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
             label: Some("uniform buffer bind group layout"),
-            entries: &[wgpu::BindGroupLayoutEntry {
-                binding: 0,
-                visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
-                ty: wgpu::BindingType::Buffer {
-                    ty: wgpu::BufferBindingType::Uniform,
-                    has_dynamic_offset: false,
-                    min_binding_size: None,
-                },
-                count: None,
-            }],
+            entries: &[uniform_buffer_entry(0), uniform_buffer_entry(1)],
         });
 
         let uniform_buffer_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("uniform buffer bind group"),
             layout: &uniform_buffer_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: uniform_buffer.as_entire_binding(),
-            }],
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: escape_time_buffer.as_entire_binding(),
+                },
+            ],
         });
 
         let shader = device.create_shader_module(wgpu::include_wgsl!("shader.wgsl"));
@@ -173,7 +288,7 @@ impl State {
             vertex: wgpu::VertexState {
                 module: &shader,
                 entry_point: Some("vertex"),
-                buffers: &[Vertex::buffer_layout()],
+                buffers: &[BufferVertex::buffer_layout(), BufferInstance::buffer_layout()],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             },
             fragment: Some(wgpu::FragmentState {
@@ -198,8 +313,36 @@ impl State {
             cache: None,
         });
 
-        let mut state = Self { surface, device, queue, config, uniform_buffer_content, vertex_buffer, index_buffer, uniform_buffer, uniform_buffer_bind_group, render_pipeline, num_indices, window, size, curve, curve_instance, surface_configured, animate, animation_value_offset, iteration };
-        state.update_buffers();
+        // evaluates escape-time fractals per pixel on a triangle covering the window
+        let escape_time_shader = device.create_shader_module(wgpu::include_wgsl!("escape_time.wgsl"));
+        let escape_time_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("escape time pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &escape_time_shader,
+                entry_point: Some("vertex"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &escape_time_shader,
+                entry_point: Some("fragment"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            multisample: wgpu::MultisampleState::default(),
+            depth_stencil: None,
+            multiview: None,
+            cache: None,
+        });
+
+        let mut state = Self { surface, device, queue, config, uniform_buffer_content, vertex_buffers, index_buffers, instance_buffers, uniform_buffer, escape_time_buffer, uniform_buffer_bind_group, render_pipeline, escape_time_pipeline, visible_indices, mesh: None, origin: DVec2::default(), camera_offset: DVec2::default(), camera_zoom: 1., window, size, curve, fractal, job, proxy, memory_budget, cached_iterations, recent_iterations: Vec::new(), surface_configured, animate, animation_value_offset, iteration, selected_parameter, cursor_position: None, dragging: false };
+        state.initialize_curve();
         state
     }
 
@@ -219,8 +362,10 @@ impl State {
                 _ => Vec2::new(1., 1.),
             };
             self.update_uniform_buffer();
+            self.update_visible_indices();
 
             self.surface_configured = true;
+            self.refine_details();
         }
     }
 
@@ -232,16 +377,323 @@ impl State {
         });
     }
 
+    /// position in window (in pixels) as position in range [-1, 1]
+    /// before scaling for aspect ratio and camera, like in the vertex shader
+    fn window_to_clip(&self, position: PhysicalPosition<f64>) -> Vec2 {
+        #[allow(clippy::cast_possible_truncation)]
+        let position = Vec2::new(position.x as f32, position.y as f32);
+        #[allow(clippy::cast_precision_loss)]
+        let size = Vec2::new(self.size.width as f32, self.size.height as f32);
+        Vec2::new(
+            (position.x / size.x).mul_add(2., -1.),
+            (position.y / size.y).mul_add(-2., 1.),
+        )
+    }
+
+    /// move camera by `delta` in range [-1, 1] of the visible area
+    fn pan_camera_by(&mut self, delta: Vec2) {
+        let delta = DVec2::from(delta / self.uniform_buffer_content.position_scale);
+        self.camera_offset = self.camera_offset - (delta / self.camera_zoom);
+        self.update_camera();
+    }
+
+    /// move camera by a step in the direction of `x`, `y` (each -1, 0 or 1)
+    pub fn pan_camera(&mut self, x: f32, y: f32) {
+        // move content the opposite way, so the camera moves in the direction
+        self.pan_camera_by(Vec2::new(-x, -y) * PAN_STEP * 2.);
+    }
+
+    /// move camera with the cursor while dragging
+    pub fn move_cursor(&mut self, position: PhysicalPosition<f64>) {
+        if let (true, Some(last_position)) = (self.dragging, self.cursor_position) {
+            let delta = self.window_to_clip(position) - self.window_to_clip(last_position);
+            self.pan_camera_by(delta);
+        }
+        self.cursor_position = Some(position);
+    }
+
+    /// multiply zoom by `factor`, keeping the curve under the cursor
+    /// (or in the center of the window without cursor) in place
+    pub fn zoom_camera(&mut self, factor: f32, at_cursor: bool) {
+        let anchor = match (at_cursor, self.cursor_position) {
+            (true, Some(position)) => self.window_to_clip(position),
+            _ => Vec2::new(0., 0.),
+        };
+
+        let max_zoom = match self.fractal {
+            Some(Fractal::EscapeTime(_)) => MAX_ESCAPE_TIME_ZOOM,
+            _ => MAX_ZOOM,
+        };
+        let zoom = (self.camera_zoom * f64::from(factor)).clamp(MIN_ZOOM, max_zoom);
+        let anchor = DVec2::from(anchor / self.uniform_buffer_content.position_scale);
+        // position of the curve at the anchor should stay the same
+        self.camera_offset = self.camera_offset + (anchor / self.camera_zoom) - (anchor / zoom);
+        self.camera_zoom = zoom;
+
+        self.update_camera();
+    }
+
+    pub fn reset_camera(&mut self) {
+        self.camera_offset = DVec2::default();
+        self.camera_zoom = 1.;
+        self.update_camera();
+    }
+
+    /// show the current camera position and zoom,
+    /// moving the origin of the buffers to the camera if it is too far away
+    fn update_camera(&mut self) {
+        if (self.camera_offset - self.origin).len() * self.camera_zoom > MAX_ORIGIN_DISTANCE {
+            self.origin = self.camera_offset;
+            self.upload_relative_buffer();
+        }
+
+        let content = &mut self.uniform_buffer_content;
+        content.camera_offset = (self.camera_offset - self.origin).to_f32();
+        content.origin = self.origin.to_f32();
+        #[allow(clippy::cast_possible_truncation)]
+        let camera_zoom = self.camera_zoom as f32;
+        content.camera_zoom = camera_zoom;
+
+        self.update_uniform_buffer();
+        self.update_visible_indices();
+        self.window.request_redraw();
+        self.refine_details();
+    }
+
+    /// area of the curve visible in the window
+    fn viewport(&self) -> Bounds {
+        // inverse of scaling in the vertex shader
+        let half_size = DVec2::new(1., 1.) / DVec2::from(self.uniform_buffer_content.position_scale) / self.camera_zoom;
+        Bounds::new(self.camera_offset - half_size, self.camera_offset + half_size)
+    }
+
+    /// draw only the tiles of the mesh in view
+    fn update_visible_indices(&mut self) {
+        let Some(mesh) = &self.mesh else { return };
+        self.visible_indices = match &mesh.tiles {
+            Some(tiles) => tiles.visible(&self.viewport()),
+            // all at once
+            None => std::iter::once(0..mesh.chunks.last().map_or(0, |chunk| chunk.range.end)).collect(),
+        };
+    }
+
+    /// size of one pixel of the window at the current zoom, see [`lod::pixel_size`]
+    fn pixel_size(&self) -> f64 {
+        lod::pixel_size(self.size.width, self.size.height, self.camera_zoom)
+    }
+
+    /// compute the current mesh again if pixels got so much smaller
+    /// that the details merged for it would become visible
+    fn refine_details(&mut self) {
+        let Some(pixel_size) = self.mesh.as_ref().and_then(|mesh| mesh.pixel_size) else { return };
+        if self.job.is_none() && self.pixel_size() * LOD_REFINE_FACTOR < pixel_size {
+            log::info!("zoomed in, computing iteration {} with more details", self.iteration + 1);
+            self.compute_iteration(self.iteration);
+        }
+    }
+
+    /// `None` (and logging why) while a computation is in progress
+    fn idle_fractal(&mut self) -> Option<&mut Fractal> {
+        if let Some(job) = &self.job {
+            log::warn!("still computing iteration {}, press ESC to cancel", job.iteration + 1);
+        }
+        self.fractal.as_mut()
+    }
+
     pub fn initialize_curve(&mut self) {
-        self.curve_instance = self.curve.new_instance();
-        self.iteration = self.curve_instance.default_iteration();
-        self.update_buffers();
+        if self.idle_fractal().is_none() {
+            return;
+        }
+
+        self.selected_parameter = 0;
+        self.recent_iterations.clear();
+        let curve_instance = match self.curve.new_instance() {
+            Fractal::Curve(curve_instance) => curve_instance,
+            Fractal::EscapeTime(escape_time) => {
+                self.fractal = Some(Fractal::EscapeTime(escape_time));
+                self.update_escape_time_buffer();
+                return;
+            },
+        };
+
+        let default_iteration = curve_instance.default_iteration();
+        // highest iteration up to the default one within limits
+        let iteration = (0..=default_iteration).rev()
+            .find(|&i| self.check_limits(&curve_instance.instanced_estimate(i)).is_ok())
+            .unwrap_or_default();
+        if iteration < default_iteration {
+            log::warn!("starting at iteration {} instead of {} to stay within limits", iteration + 1, default_iteration + 1);
+        }
+        self.fractal = Some(Fractal::Curve(curve_instance));
+        self.compute_iteration(iteration);
+    }
+
+    /// `!next == previous` <br>
+    /// does nothing while computing, so the registry stays at the curve that is shown
+    pub fn switch_curve(&mut self, next: bool) {
+        if self.idle_fractal().is_none() {
+            return;
+        }
+        if next {
+            self.curve.next();
+        } else {
+            self.curve.prev();
+        }
+        self.initialize_curve();
+    }
+
+    /// `!increment == decrement` <br>
+    /// for escape-time fractals: double/halve their max. number of iterations instead
+    pub fn step_iteration(&mut self, increment: bool) {
+        match self.idle_fractal() {
+            None => (),
+            Some(Fractal::Curve(_)) => {
+                if increment {
+                    self.compute_iteration(self.iteration + 1);
+                } else if self.iteration > 0 {
+                    self.compute_iteration(self.iteration - 1);
+                }
+            },
+            Some(Fractal::EscapeTime(escape_time)) => {
+                let index = EscapeTime::MAX_ITERATIONS;
+                let value = escape_time.parameters()[index].value();
+                if escape_time.set_parameter(index, if increment { value * 2. } else { value / 2. }) {
+                    log::info!("set {}", escape_time.parameters()[index]);
+                    self.update_escape_time_buffer();
+                }
+            },
+        }
+    }
+
+    /// `!increment == decrement` <br>
+    /// does nothing if the current fractal has no parameter at `index`
+    pub fn step_parameter(&mut self, index: usize, increment: bool) {
+        let Some(fractal) = self.idle_fractal() else { return };
+        if index >= fractal.parameters().len() {
+            return;
+        }
+
+        if fractal.step_parameter(index, increment) {
+            log::info!("set {}", fractal.parameters()[index]);
+            self.update_fractal();
+        }
+    }
+
+    /// does nothing if the current fractal has no parameters
+    pub fn select_next_parameter(&mut self) {
+        let Some(fractal) = self.fractal.as_ref() else { return };
+        let parameters = fractal.parameters();
+        if parameters.is_empty() {
+            return;
+        }
+
+        self.selected_parameter = (self.selected_parameter + 1) % parameters.len();
+        log::info!("selected {}", parameters[self.selected_parameter]);
+    }
+
+    /// does nothing if the current fractal has no parameters
+    pub fn reset_selected_parameter(&mut self) {
+        let index = self.selected_parameter;
+        let Some(fractal) = self.idle_fractal() else { return };
+        if index >= fractal.parameters().len() {
+            return;
+        }
+
+        let default = fractal.parameters()[index].default;
+        if fractal.set_parameter(index, default) {
+            log::info!("set {}", fractal.parameters()[index]);
+            self.update_fractal();
+        }
+    }
+
+    /// show the current fractal after its parameters changed
+    fn update_fractal(&mut self) {
+        match self.fractal {
+            Some(Fractal::Curve(_)) => self.compute_iteration(self.iteration),
+            Some(Fractal::EscapeTime(_)) => self.update_escape_time_buffer(),
+            None => (),
+        }
+    }
+
+    /// export current iteration as svg to the working directory
+    pub fn export_svg(&mut self) {
+        let iteration = self.iteration;
+        // as detailed as in the window
+        let pixel_size = self.pixel_size();
+        let curve_instance = match self.idle_fractal() {
+            None => return,
+            Some(Fractal::Curve(curve_instance)) => curve_instance,
+            Some(Fractal::EscapeTime(_)) => {
+                log::warn!("escape-time fractals are evaluated per pixel and cannot be exported as svg");
+                return;
+            },
+        };
+
+        let path = std::path::PathBuf::from(format!("fractal-{}.svg", iteration + 1));
+        if let Err(e) = svg::export(&mut **curve_instance, iteration, Some(pixel_size), &path) {
+            log::error!("could not write {}: {e}", path.display());
+        }
+    }
+
+    /// start computing buffer contents for `iteration` on a separate thread,
+    /// keeping the current buffers until done.
+    /// does nothing while another computation is in progress, if the current fractal
+    /// is not a curve or if the iteration is estimated to exceed the memory budget or buffer size limit
+    pub fn compute_iteration(&mut self, iteration: usize) {
+        let Some(Fractal::Curve(curve_instance)) = self.idle_fractal() else { return };
+
+        let estimate = curve_instance.instanced_estimate(iteration);
+        if let Err(reason) = self.check_limits(&estimate) {
+            log::warn!("not computing iteration {} ({estimate}), {reason}", iteration + 1);
+            return;
+        }
+
+        let Some(Fractal::Curve(curve_instance)) = self.fractal.take() else { unreachable!() };
+        let max_buffer_bytes = self.device.limits().max_buffer_size;
+        self.job = Some(Job::start(curve_instance, iteration, self.pixel_size(), max_buffer_bytes, self.proxy.clone()));
+    }
+
+    /// `Err` with the reason if `estimate` exceeds the memory budget or cannot be indexed
+    /// (buffers too large for this device are split, see [`vertex::chunks`])
+    fn check_limits(&self, estimate: &Estimate) -> Result<(), String> {
+        if estimate.indices() > MAX_INDICES {
+            return Err(format!("exceeds the maximum of {MAX_INDICES} indices"));
+        }
+        if estimate.memory_bytes > self.memory_budget {
+            return Err(format!("exceeds memory budget of {} MiB", mib(self.memory_budget)));
+        }
+        Ok(())
+    }
+
+    pub const fn is_computing(&self) -> bool {
+        self.job.is_some()
+    }
+
+    /// buffers stay the same, does nothing if there is no computation in progress
+    pub fn cancel_computation(&self) {
+        if let Some(job) = &self.job {
+            log::info!("cancelling computation of iteration {}", job.iteration + 1);
+            job.cancel();
+        }
     }
 
-    /// remove data of curve instance except for starting iteration
-    pub fn redo_curve(&mut self) {
-        *self.curve_instance.mut_data() = vec![self.curve_instance.data()[0].clone()];
-        self.update_buffers();
+    /// take back curve instance from worker thread and
+    /// update buffers if the computation was not cancelled
+    pub fn finish_computation(&mut self, mut curve_instance: Box<dyn Curve>, mesh: Option<Mesh>) {
+        self.job = None;
+        if let Some(mesh) = &mesh {
+            self.recent_iterations.retain(|&i| i != mesh.iteration);
+            self.recent_iterations.push(mesh.iteration);
+            let evicted = self.recent_iterations.len().saturating_sub(self.cached_iterations);
+            self.recent_iterations.drain(..evicted);
+        }
+        // also the ones computed on the way
+        curve_instance.evict(&self.recent_iterations);
+
+        self.fractal = Some(Fractal::Curve(curve_instance));
+        if let Some(mesh) = mesh {
+            self.update_buffers(mesh);
+        }
     }
 
     pub fn update_uniform_buffer(&self) {
@@ -249,6 +701,14 @@ impl State {
             bytemuck::cast_slice(&[self.uniform_buffer_content]));
     }
 
+    /// does nothing if the current fractal is not an escape-time fractal
+    fn update_escape_time_buffer(&self) {
+        let Some(Fractal::EscapeTime(escape_time)) = &self.fractal else { return };
+        self.queue.write_buffer(&self.escape_time_buffer, 0,
+            bytemuck::cast_slice(&[EscapeTimeBufferContent::new(escape_time)]));
+        self.window.request_redraw();
+    }
+
     /// `set_offset` should only be `true` once when the animation was
     /// stopped for some time and should start again on the next frame
     pub fn update_animation_value(&mut self, set_offset: bool) {
@@ -281,55 +741,67 @@ impl State {
         }
     }
 
-    pub fn update_buffers(&mut self) {
-        use bytemuck::cast_slice;
-
-        let vertex_format = self.curve_instance.vertex_format();
-        let vertices = self.curve_instance.vertices(self.iteration);
-
-        let vertices = match vertex_format {
-            VertexFormat::Lines => &vertex::lines_as_triangles(vertices, LINE_WIDTH),
-            VertexFormat::Triangles => vertices,
-        };
-
-        let (vertices, indices) = vertex::index(vertices);
-
-        self.num_indices = indices.len().try_into().unwrap();
-
-        self.uniform_buffer_content.max_iteration = vertices.par_iter()
-            .map(|v| v.iteration)
-            .max()
-            .unwrap();
-
-        // cast buffer data to slice of bytes
-        let vertices = cast_slice(vertices.as_slice());
-        let indices = cast_slice(indices.as_slice());
-
-        if vertices.len() as u64 > self.device.limits().max_buffer_size {
-            log::error!("computed vertices are too large to buffer on this device");
-            std::process::exit(1);
+    fn update_buffers(&mut self, mesh: Mesh) {
+        self.iteration = mesh.iteration;
+        self.uniform_buffer_content.max_iteration = mesh.max_iteration;
+
+        self.index_buffers = mesh.chunks.iter()
+            .map(|chunk| self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("index buffer"),
+                usage: wgpu::BufferUsages::INDEX,
+                contents: bytemuck::cast_slice(chunk.indices.as_slice()),
+            }))
+            .collect();
+
+        // only one of vertices and instances depends on the origin
+        if mesh.instances.is_some() {
+            // base mesh (the copies are relative to the origin)
+            self.vertex_buffers = mesh.chunks.iter()
+                .map(|chunk| self.create_vertex_buffer("vertex buffer",
+                    &vertex::to_buffer(&chunk.vertices, DVec2::default())))
+                .collect();
+        } else {
+            // just one copy of the vertices, as they are
+            self.instance_buffers = self.create_instance_buffers(
+                &[BufferInstance::new(&instanced::Instance::IDENTITY, DVec2::default())]);
         }
 
-        self.vertex_buffer = Some(self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("vertex buffer"),
-            usage: wgpu::BufferUsages::VERTEX,
-            contents: vertices,
-        }));
-
-        self.index_buffer = Some(self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("index buffer"),
-            usage: wgpu::BufferUsages::INDEX,
-            contents: indices,
-        }));
-
+        self.mesh = Some(mesh);
+        // new buffers might as well be relative to the camera
+        self.origin = self.camera_offset;
+        self.upload_relative_buffer();
+        self.update_camera();
+    }
 
-        if self.animate {
-            // uniform buffer will be updated later in the loop either way
-        } else {
-            self.update_uniform_buffer();
+    /// upload the vertices or instances of the mesh (whichever depends on it) relative to the origin
+    fn upload_relative_buffer(&mut self) {
+        let Some(mesh) = &self.mesh else { return };
+
+        match &mesh.instances {
+            None => self.vertex_buffers = mesh.chunks.iter()
+                .map(|chunk| self.create_vertex_buffer("vertex buffer",
+                    &vertex::to_buffer(&chunk.vertices, self.origin)))
+                .collect(),
+            Some(instances) => self.instance_buffers = self.create_instance_buffers(
+                &instanced::to_buffer(instances, self.origin)),
         }
+    }
 
-        self.window.request_redraw();
+    /// as many buffers as needed to stay within the buffer size limit of this device
+    fn create_instance_buffers(&self, instances: &[BufferInstance]) -> Vec<(wgpu::Buffer, u32)> {
+        let max_bytes = usize::try_from(self.device.limits().max_buffer_size).unwrap_or(usize::MAX);
+        let max_instances = (max_bytes / size_of::<BufferInstance>()).max(1);
+        instances.chunks(max_instances)
+            .map(|chunk| (self.create_vertex_buffer("instance buffer", chunk), chunk.len().try_into().unwrap()))
+            .collect()
+    }
+
+    fn create_vertex_buffer<T: bytemuck::Pod>(&self, label: &str, contents: &[T]) -> wgpu::Buffer {
+        self.device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(label),
+            usage: wgpu::BufferUsages::VERTEX,
+            contents: bytemuck::cast_slice(contents),
+        })
     }
 
     pub fn render(&self) -> Result<(), wgpu::SurfaceError> {
@@ -361,11 +833,25 @@ impl State {
             ..Default::default()
         });
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.uniform_buffer_bind_group, &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.as_ref().unwrap().slice(..));
-        render_pass.set_index_buffer(self.index_buffer.as_ref().unwrap().slice(..), wgpu::IndexFormat::Uint32);
-        render_pass.draw_indexed(0..self.num_indices, 0, 0..1);
+        if matches!(self.fractal, Some(Fractal::EscapeTime(_))) {
+            render_pass.set_pipeline(&self.escape_time_pipeline);
+            render_pass.set_bind_group(0, &self.uniform_buffer_bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        } else if let Some(mesh) = &self.mesh {
+            // (there are no buffers until the first computation is done)
+            render_pass.set_pipeline(&self.render_pipeline);
+            render_pass.set_bind_group(0, &self.uniform_buffer_bind_group, &[]);
+            for (instance_buffer, instances) in &self.instance_buffers {
+                render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                for ((chunk, vertex_buffer), index_buffer) in mesh.chunks.iter().zip(&self.vertex_buffers).zip(&self.index_buffers) {
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    for indices in self.visible_indices.iter().filter_map(|indices| chunk.local(indices)) {
+                        render_pass.draw_indexed(indices, 0, 0..*instances);
+                    }
+                }
+            }
+        }
 
         // free encoder borrow
         drop(render_pass);
@@ -377,3 +863,4 @@ impl State {
         Ok(())
     }
 }
+
@@ -2,21 +2,35 @@ use std::sync::Arc;
 use winit::{
     application::ApplicationHandler,
     error::EventLoopError,
-    event::{ElementState, KeyEvent, WindowEvent},
-    event_loop::{ActiveEventLoop, EventLoop},
-    keyboard::{KeyCode, PhysicalKey},
+    event::{ElementState, KeyEvent, MouseButton, MouseScrollDelta, WindowEvent},
+    event_loop::{ActiveEventLoop, EventLoop, EventLoopProxy},
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
     window::{Icon, Window, WindowId}
 };
 
-use super::state::State;
-use crate::curves::{Curve, Curves, canopy::Canopy};
+use super::{state::State, worker::UserEvent};
+use crate::curves::{Registry, definition::Definition};
+
+const TITLE: &str = "Fractals";
+/// camera zoom factor per scrolled line or key press
+const ZOOM_STEP: f32 = 1.25;
+/// scrolled pixels (e.g. on touchpads) equivalent to one line
+const PIXELS_PER_LINE: f64 = 50.;
 
-#[derive(Default)]
 struct App {
     state: Option<State>,
+    /// moved into state on startup
+    definitions: Vec<Arc<Definition>>,
+    proxy: EventLoopProxy<UserEvent>,
+    /// in bytes
+    memory_budget: usize,
+    /// see [`State::new`]
+    cached_iterations: usize,
+    /// modifier keys currently pressed
+    modifiers: ModifiersState,
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<UserEvent> for App {
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
         let icon = {
             // store icon in executable so we can still distribute just a single file
@@ -31,24 +45,16 @@ impl ApplicationHandler for App {
         let window = Arc::new(
             event_loop.create_window(
                 Window::default_attributes()
-                    .with_title("Fractals")
+                    .with_title(TITLE)
                     .with_window_icon(Some(icon))
             ).unwrap()
         );
 
-        let state = pollster::block_on(State::new(window.clone()));
+        let registry = Registry::new(std::mem::take(&mut self.definitions));
+        let state = pollster::block_on(State::new(window.clone(), registry, self.proxy.clone(), self.memory_budget, self.cached_iterations));
 
         state.set_control_flow(event_loop);
 
-        // if (probably) profiling: exit here before entering the infinite event loop
-        if let Ok(value) = std::env::var("CARGO_PROFILE_RELEASE_DEBUG") {
-            if value == "true" {
-                log::info!("detected environment variable CARGO_PROFILE_RELEASE_DEBUG=true");
-                log::info!("early-exiting now before entering event loop");
-                std::process::exit(0);
-            }
-        }
-
         window.request_redraw();
 
         self.state = Some(state);
@@ -68,46 +74,51 @@ impl ApplicationHandler for App {
                 },
                 ..
             } => match key {
-                ArrowUp => {
-                    state.iteration += 1;
-                    state.update_buffers();
-                },
-                ArrowDown => {
-                    if state.iteration > 0 {
-                        state.iteration -= 1;
-                        state.update_buffers();
-                    }
-                },
+                // move camera, takes precedence over other uses of the arrow keys
+                key @ (ArrowUp | ArrowDown | ArrowLeft | ArrowRight) if self.modifiers.shift_key() => {
+                    let (x, y) = match key {
+                        ArrowUp    => ( 0.,  1.),
+                        ArrowDown  => ( 0., -1.),
+                        ArrowLeft  => (-1.,  0.),
+                        ArrowRight => ( 1.,  0.),
+                        _ => panic!(),
+                    };
 
-                ArrowLeft => {
-                    state.curve.prev();
-                    state.initialize_curve();
-                },
-                ArrowRight => {
-                    state.curve.next();
-                    state.initialize_curve();
+                    state.pan_camera(x, y);
                 },
 
-                key @ (KeyF | KeyD | KeyJ | KeyK) => {
-                    if state.curve != Curves::Canopy {
-                        return;
-                    }
+                ArrowUp => state.step_iteration(true),
+                ArrowDown | Escape if state.is_computing() => state.cancel_computation(),
+                ArrowDown => state.step_iteration(false),
+
+                ArrowLeft => state.switch_curve(false),
+                ArrowRight => state.switch_curve(true),
 
-                    let (increment, left) = match key {
-                        KeyF => ( true,  true),
-                        KeyD => (false,  true),
-                        KeyJ => ( true, false),
-                        KeyK => (false, false),
+                // adjust first two parameters directly
+                key @ (KeyF | KeyD | KeyJ | KeyK) => {
+                    let (index, increment) = match key {
+                        KeyF => (0,  true),
+                        KeyD => (0, false),
+                        KeyJ => (1,  true),
+                        KeyK => (1, false),
                         _ => panic!(),
                     };
 
-                    if Canopy::downcast(&mut state.curve_instance)
-                        .change_angle(increment, left)
-                    {
-                        state.redo_curve();
-                    }
+                    state.step_parameter(index, increment);
                 },
 
+                // adjust any parameter by selecting it first
+                Tab => state.select_next_parameter(),
+                key @ (PageUp | PageDown) =>
+                    state.step_parameter(state.selected_parameter, key == PageUp),
+                Backspace => state.reset_selected_parameter(),
+
+                KeyE => state.export_svg(),
+
+                Equal | NumpadAdd => state.zoom_camera(ZOOM_STEP, false),
+                Minus | NumpadSubtract => state.zoom_camera(1. / ZOOM_STEP, false),
+                Digit0 | Numpad0 => state.reset_camera(),
+
                 F11 => {
                     state.window.set_fullscreen(match state.window.fullscreen() {
                         None => Some(winit::window::Fullscreen::Borderless(None)),
@@ -164,6 +175,28 @@ impl ApplicationHandler for App {
                 }
             }
 
+            WindowEvent::ModifiersChanged(modifiers) => self.modifiers = modifiers.state(),
+
+            WindowEvent::MouseWheel { delta, .. } => {
+                #[allow(clippy::cast_possible_truncation)]
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => (position.y / PIXELS_PER_LINE) as f32,
+                };
+
+                state.zoom_camera(ZOOM_STEP.powf(lines), true);
+            },
+
+            WindowEvent::MouseInput { state: button_state, button: MouseButton::Left, .. } =>
+                state.dragging = button_state == ElementState::Pressed,
+
+            WindowEvent::CursorMoved { position, .. } => state.move_cursor(position),
+
+            WindowEvent::CursorLeft { .. } => {
+                state.cursor_position = None;
+                state.dragging = false;
+            },
+
             WindowEvent::Resized(physical_size) => {
                 // this also (re)configures the surface 
                 state.resize(physical_size);
@@ -174,10 +207,32 @@ impl ApplicationHandler for App {
             _ => (),
         }
     }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, event: UserEvent) {
+        let Some(state) = self.state.as_mut() else { return };
+        match event {
+            UserEvent::Progress(stage) =>
+                state.window.set_title(&format!("{TITLE} ({stage}...)")),
+
+            UserEvent::Done { curve_instance, mesh } => {
+                state.window.set_title(TITLE);
+                state.finish_computation(curve_instance, mesh);
+
+                // if (probably) profiling: exit here after the first computation
+                if let Ok(value) = std::env::var("CARGO_PROFILE_RELEASE_DEBUG") {
+                    if value == "true" {
+                        log::info!("detected environment variable CARGO_PROFILE_RELEASE_DEBUG=true");
+                        log::info!("early-exiting now after computing the first iteration");
+                        std::process::exit(0);
+                    }
+                }
+            },
+        }
+    }
 }
 
-pub fn run_app() {
-    let event_loop = match EventLoop::new() {
+pub fn run_app(definitions: Vec<Arc<Definition>>, memory_budget: usize, cached_iterations: usize) {
+    let event_loop = match EventLoop::with_user_event().build() {
         Ok(event_loop) => event_loop,
 
         #[cfg(target_os = "linux")]
@@ -207,6 +262,7 @@ pub fn run_app() {
         Err(e) => panic!("{e:?}")
     };
 
-    let mut app = App::default();
+    let proxy = event_loop.create_proxy();
+    let mut app = App { state: None, definitions, proxy, memory_budget, cached_iterations, modifiers: ModifiersState::empty() };
     event_loop.run_app(&mut app).unwrap();
 }
@@ -0,0 +1,176 @@
+use std::{fmt, sync::{Arc, atomic::{AtomicBool, Ordering}}};
+use rayon::prelude::*;
+use winit::event_loop::EventLoopProxy;
+
+use crate::curves::Curve;
+use crate::vertex::{self, POINT_SIZE, VertexFormat, chunks::{self, Chunk}, instanced::Instance, tiles::Quadtree};
+
+/// sent from the worker thread to the event loop
+pub enum UserEvent {
+    Progress(Stage),
+    Done {
+        /// handed back after being moved to the worker thread
+        curve_instance: Box<dyn Curve>,
+        /// `None` if computation was cancelled
+        mesh: Option<Mesh>,
+    },
+}
+
+/// what the worker thread is currently doing
+#[derive(Clone, Copy)]
+pub enum Stage {
+    ComputingIteration(usize),
+    ComputingInstances(usize),
+    Simplifying,
+    RepresentingLinesAsTriangles,
+    RepresentingPointsAsTriangles,
+    Indexing,
+    PartitioningIntoTiles,
+    SplittingIntoChunks,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ComputingIteration(iteration) => write!(f, "computing iteration {}", iteration + 1),
+            Self::ComputingInstances(iteration) => write!(f, "computing instances of iteration {}", iteration + 1),
+            Self::Simplifying => write!(f, "merging details smaller than a pixel"),
+            Self::RepresentingLinesAsTriangles => write!(f, "representing lines as triangles"),
+            Self::RepresentingPointsAsTriangles => write!(f, "representing points as triangles"),
+            Self::Indexing => write!(f, "indexing vertices"),
+            Self::PartitioningIntoTiles => write!(f, "partitioning triangles into tiles"),
+            Self::SplittingIntoChunks => write!(f, "splitting triangles to fit into buffers"),
+        }
+    }
+}
+
+/// contents for vertex, index and instance buffers
+pub struct Mesh {
+    pub iteration: usize,
+    /// indexed triangles, split to fit into buffers
+    pub chunks: Vec<Chunk>,
+    /// copies of the triangles to draw, `None` without instancing
+    pub instances: Option<Vec<Instance>>,
+    /// highest iteration value present in the copies of the triangles
+    pub max_iteration: u32,
+    /// details smaller than this were merged or dropped, see [`vertex::lod`].
+    /// `None` with instancing, which keeps every detail
+    pub pixel_size: Option<f64>,
+    /// to only draw the triangles in view, `None` with instancing,
+    /// as every copy is somewhere else
+    pub tiles: Option<Quadtree>,
+}
+
+/// handle to a computation running on a separate thread
+pub struct Job {
+    /// iteration being computed
+    pub iteration: usize,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Job {
+    /// compute mesh for `iteration` of `curve_instance` on a separate thread,
+    /// sending progress and the result as [`UserEvent`]s to `proxy`.
+    /// details smaller than `pixel_size` are merged or dropped,
+    /// and no buffer will be larger than `max_buffer_bytes`
+    pub fn start(mut curve_instance: Box<dyn Curve>, iteration: usize, pixel_size: f64, max_buffer_bytes: u64, proxy: EventLoopProxy<UserEvent>) -> Self {
+        let cancel = Arc::new(AtomicBool::new(false));
+
+        let thread_cancel = cancel.clone();
+        std::thread::spawn(move || {
+            let progress = |stage| {
+                // event loop might already be closed, nothing to do then
+                let _ = proxy.send_event(UserEvent::Progress(stage));
+            };
+
+            let mesh = compute_mesh(&mut *curve_instance, iteration, pixel_size, max_buffer_bytes, &thread_cancel, progress);
+
+            let _ = proxy.send_event(UserEvent::Done { curve_instance, mesh });
+        });
+
+        Self { iteration, cancel }
+    }
+
+    /// result will still be sent, but without mesh
+    pub fn cancel(&self) {
+        self.cancel.store(true, Ordering::Relaxed);
+    }
+}
+
+/// returns `None` as soon as `cancel` is set
+fn compute_mesh(
+    curve_instance: &mut dyn Curve,
+    iteration: usize,
+    pixel_size: f64,
+    max_buffer_bytes: u64,
+    cancel: &AtomicBool,
+    progress: impl Fn(Stage),
+) -> Option<Mesh> {
+    let is_cancelled = || cancel.load(Ordering::Relaxed);
+
+    if let Some(instancing) = curve_instance.instancing() {
+        progress(Stage::ComputingInstances(iteration));
+        let instanced = instancing.instanced(iteration);
+        if is_cancelled() {
+            return None;
+        }
+
+        progress(Stage::Indexing);
+        let max_iteration = instanced.max_iteration();
+        let (vertices, indices) = vertex::index(&instanced.base);
+        log::info!("drawing them as {} instances", instanced.instances.len());
+        let chunks = chunks::split(vertices, indices, max_buffer_bytes);
+
+        return Some(Mesh { iteration, chunks, instances: Some(instanced.instances), max_iteration, pixel_size: None, tiles: None });
+    }
+
+    progress(Stage::ComputingIteration(iteration));
+    let vertex_format = curve_instance.vertex_format();
+    let stroke = curve_instance.stroke();
+    let vertices = curve_instance.try_vertices(iteration, cancel)?;
+
+    progress(Stage::Simplifying);
+    let (vertices, _) = vertex::lod::simplify(vertices, vertex_format, pixel_size);
+    let vertices = &vertices;
+    if is_cancelled() {
+        return None;
+    }
+
+    let vertices = match vertex_format {
+        VertexFormat::Lines => {
+            progress(Stage::RepresentingLinesAsTriangles);
+            &vertex::lines_as_triangles(vertices, &stroke)
+        },
+        VertexFormat::Triangles => vertices,
+        VertexFormat::Points => {
+            progress(Stage::RepresentingPointsAsTriangles);
+            &vertex::points_as_triangles(vertices, POINT_SIZE)
+        },
+    };
+    if is_cancelled() {
+        return None;
+    }
+
+    progress(Stage::Indexing);
+    let (vertices, mut indices) = vertex::index(vertices);
+    if is_cancelled() {
+        return None;
+    }
+
+    progress(Stage::PartitioningIntoTiles);
+    let tiles = Quadtree::new(&vertices, &mut indices);
+    if is_cancelled() {
+        return None;
+    }
+
+    // empty if e.g. all points of a chaos game diverged
+    let max_iteration = vertices.par_iter()
+        .map(|v| v.iteration)
+        .max()
+        .unwrap_or(0);
+
+    progress(Stage::SplittingIntoChunks);
+    let chunks = chunks::split(vertices, indices, max_buffer_bytes);
+
+    Some(Mesh { iteration, chunks, instances: None, max_iteration, pixel_size: Some(pixel_size), tiles: Some(tiles) })
+}
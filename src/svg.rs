@@ -0,0 +1,151 @@
+//! resolution-independent export of curve vertices,
+//! colored like in the window (without animation)
+
+use std::{fs::File, io::{self, BufWriter, Write}, path::Path};
+
+use crate::color::{self, Rgb};
+use crate::curves::Curve;
+use crate::vertex::{POINT_SIZE, Vertex, VertexFormat, lod, stroke::{Cap, Stroke}, vec2::DVec2};
+
+/// max. sine of the angle between two segments to still count as collinear
+const COLLINEAR_TOLERANCE: f64 = 1e-5;
+
+/// consecutive collinear lines of the same iteration
+struct Segment {
+    a: DVec2,
+    b: DVec2,
+    iteration: u32,
+}
+
+/// write `vertices` (as returned by [`crate::curves::Curve::vertices`]) as svg to `out`.
+/// consecutive connected collinear lines of the same iteration are merged into one line
+/// (to keep file sizes sane), triangles are written as filled polygons and points as squares.
+/// lines get the width and caps of `stroke`, but no joins, as every line is a separate element
+/// (of its own color)
+pub fn write(mut out: impl Write, vertices: &[Vertex], vertex_format: VertexFormat, stroke: &Stroke) -> io::Result<()> {
+    let max_iteration = vertices.iter()
+        .map(|v| v.iteration)
+        .max()
+        .unwrap_or_default();
+
+    // same coordinates as in the window, but with y pointing down
+    writeln!(out, r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="-1 -1 2 2">"#)?;
+    writeln!(out, r#"<rect x="-1" y="-1" width="2" height="2" fill="black"/>"#)?;
+
+    match vertex_format {
+        VertexFormat::Lines => {
+            let cap = match stroke.cap { Cap::Butt => "butt", Cap::Square => "square", Cap::Round => "round" };
+            writeln!(out, r#"<g fill="none" stroke-width="{}" stroke-linecap="{cap}">"#, stroke.width)?;
+            for segment in segments(vertices) {
+                // color at the center of the segment, as there are no gradients along lines in svg
+                let center = segment.a + ((segment.b - segment.a) / 2.);
+                let color = color::vertex_color(&Vertex::new(center, segment.iteration), max_iteration, 0.);
+                write!(out, r#"<line stroke="{}" x1="{}" y1="{}" x2="{}" y2="{}""#,
+                    hex(color), segment.a.x, -segment.a.y, segment.b.x, -segment.b.y)?;
+                // no tapering in svg, so the width of the newer end
+                #[allow(clippy::float_cmp)]
+                if stroke.falloff != 1. {
+                    write!(out, r#" stroke-width="{}""#, stroke.width(segment.iteration))?;
+                }
+                writeln!(out, "/>")?;
+            }
+            writeln!(out, "</g>")?;
+        },
+        VertexFormat::Triangles => {
+            for triangle in vertices.chunks_exact(3) {
+                // average of the vertex colors, as there are no gradients along triangles in svg
+                let colors = triangle.iter().map(|v| color::vertex_color(v, max_iteration, 0.));
+                let color = colors.fold([0.; 3], |sum, c| [0, 1, 2].map(|i| sum[i] + c[i] / 3.));
+
+                write!(out, r#"<polygon fill="{}" points=""#, hex(color))?;
+                write_points(&mut out, &triangle.iter().map(|v| v.position).collect::<Vec<_>>())?;
+                writeln!(out, r#""/>"#)?;
+            }
+        },
+        VertexFormat::Points => {
+            // squares like in the window
+            for point in vertices {
+                let color = color::vertex_color(point, max_iteration, 0.);
+                let top_left = point.position + DVec2::new(-POINT_SIZE / 2., POINT_SIZE / 2.);
+                writeln!(out, r#"<rect fill="{}" x="{}" y="{}" width="{POINT_SIZE}" height="{POINT_SIZE}"/>"#,
+                    hex(color), top_left.x, -top_left.y)?;
+            }
+        },
+    }
+
+    writeln!(out, "</svg>")
+}
+
+/// write `iteration` of `curve_instance` (computing it if necessary) to a file at `path`,
+/// without details smaller than `pixel_size` if given (see [`lod::simplify`])
+pub fn export(curve_instance: &mut dyn Curve, iteration: usize, pixel_size: Option<f64>, path: &Path) -> io::Result<()> {
+    let vertex_format = curve_instance.vertex_format();
+    let stroke = curve_instance.stroke();
+    // same vertices, but only the ones of this iteration are computed
+    let flattened;
+    let vertices = match curve_instance.instancing() {
+        Some(instancing) => {
+            flattened = instancing.instanced(iteration).flatten();
+            &flattened
+        },
+        None => curve_instance.vertices(iteration),
+    };
+    let simplified;
+    let vertices = match pixel_size {
+        Some(pixel_size) => {
+            simplified = lod::simplify(vertices, vertex_format, pixel_size).0;
+            &simplified
+        },
+        None => vertices,
+    };
+
+    let mut out = BufWriter::new(File::create(path)?);
+    write(&mut out, vertices, vertex_format, &stroke)?;
+    out.flush()?;
+
+    log::info!("exported iteration {} to {}", iteration + 1, path.display());
+    Ok(())
+}
+
+/// iteration of a line, as the newer one of its vertices
+fn line_iteration(line: &[Vertex]) -> u32 {
+    line[0].iteration.max(line[1].iteration)
+}
+
+/// merge consecutive connected collinear lines of the same iteration
+fn segments(vertices: &[Vertex]) -> Vec<Segment> {
+    let mut segments = Vec::<Segment>::new();
+
+    for line in vertices.chunks_exact(2) {
+        let (a, b) = (line[0].position, line[1].position);
+        let iteration = line_iteration(line);
+
+        match segments.last_mut() {
+            Some(last) if last.iteration == iteration && last.b == a && collinear(last.b - last.a, b - a) =>
+                last.b = b,
+            _ => segments.push(Segment { a, b, iteration }),
+        }
+    }
+
+    segments
+}
+
+/// whether `a` and `b` point in the same direction
+fn collinear(a: DVec2, b: DVec2) -> bool {
+    let cross = a.x.mul_add(b.y, -(a.y * b.x));
+    let dot = a.x.mul_add(b.x, a.y * b.y);
+    dot > 0. && cross.abs() <= COLLINEAR_TOLERANCE * a.len() * b.len()
+}
+
+fn write_points(out: &mut impl Write, points: &[DVec2]) -> io::Result<()> {
+    for (i, point) in points.iter().enumerate() {
+        let separator = if i == 0 { "" } else { " " };
+        write!(out, "{separator}{},{}", point.x, -point.y)?;
+    }
+    Ok(())
+}
+
+fn hex(color: Rgb) -> String {
+    let [r, g, b] = color::to_srgb8(color);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
@@ -0,0 +1,81 @@
+use super::vec2::DVec2;
+
+/// linear transformation followed by a translation, see
+/// <https://en.wikipedia.org/wiki/Affine_transformation>
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Affine {
+    /// row by row, so `x' = matrix[0][0] * x + matrix[0][1] * y`
+    pub matrix: [[f64; 2]; 2],
+    pub offset: DVec2,
+}
+
+impl Affine {
+    pub const fn new(matrix: [[f64; 2]; 2], offset: DVec2) -> Self {
+        Self { matrix, offset }
+    }
+
+    /// scale uniformly by `factor`, then translate by `offset`
+    pub const fn scale(factor: f64, offset: DVec2) -> Self {
+        Self::new([[factor, 0.], [0., factor]], offset)
+    }
+
+    /// scale uniformly by `factor` and rotate counterclockwise by `angle` (in radians),
+    /// then translate by `offset`
+    pub fn rotate_scale(angle: f64, factor: f64, offset: DVec2) -> Self {
+        let (sin, cos) = (angle.sin() * factor, angle.cos() * factor);
+        Self::new([[cos, -sin], [sin, cos]], offset)
+    }
+
+    /// same transformation, but translated so that `from` is mapped to `to`
+    pub fn mapping(self, from: DVec2, to: DVec2) -> Self {
+        Self::new(self.matrix, to - self.linear(from))
+    }
+
+    pub fn apply(&self, point: DVec2) -> DVec2 {
+        self.linear(point) + self.offset
+    }
+
+    /// only the linear transformation, e.g. for directions
+    pub fn linear(&self, vector: DVec2) -> DVec2 {
+        let [[a, b], [c, d]] = self.matrix;
+        DVec2::new(
+            a.mul_add(vector.x, b * vector.y),
+            c.mul_add(vector.x, d * vector.y),
+        )
+    }
+
+    /// `self` applied after `other`
+    pub fn after(&self, other: &Self) -> Self {
+        let [[a, b], [c, d]] = self.matrix;
+        let [[e, f], [g, h]] = other.matrix;
+        Self::new(
+            [
+                [a.mul_add(e, b * g), a.mul_add(f, b * h)],
+                [c.mul_add(e, d * g), c.mul_add(f, d * h)],
+            ],
+            self.apply(other.offset),
+        )
+    }
+
+    /// negative if the transformation mirrors, which reverses the winding order of triangles
+    pub fn determinant(&self) -> f64 {
+        let [[a, b], [c, d]] = self.matrix;
+        a.mul_add(d, -(b * c))
+    }
+
+    /// `None` if the transformation is not invertible (determinant 0)
+    pub fn inverse(&self) -> Option<Self> {
+        let [[a, b], [c, d]] = self.matrix;
+        let determinant = self.determinant();
+        if determinant == 0. {
+            return None;
+        }
+
+        let matrix = [
+            [ d / determinant, -b / determinant],
+            [-c / determinant,  a / determinant],
+        ];
+        let linear = Self::new(matrix, DVec2::new(0., 0.));
+        Some(Self::new(matrix, -linear.linear(self.offset)))
+    }
+}
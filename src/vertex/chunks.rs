@@ -0,0 +1,134 @@
+//! meshes too large for a single buffer of the gpu, split into several
+
+use std::{mem::size_of, ops::Range};
+
+use super::{BufferVertex, Vertex};
+
+/// consecutive triangles of a mesh, small enough for one vertex and one index buffer
+#[derive(Clone, Debug)]
+pub struct Chunk {
+    /// only the ones used by the triangles of this chunk
+    pub vertices: Vec<Vertex>,
+    /// into `vertices` of this chunk
+    pub indices: Vec<u32>,
+    /// position of `indices` in the indices of the whole mesh
+    pub range: Range<u32>,
+}
+
+impl Chunk {
+    /// part of `range` (of indices of the whole mesh) in this chunk,
+    /// as range of `indices` of this chunk. `None` if there is no such part
+    pub fn local(&self, range: &Range<u32>) -> Option<Range<u32>> {
+        let start = range.start.max(self.range.start);
+        let end = range.end.min(self.range.end);
+        (start < end).then(|| (start - self.range.start)..(end - self.range.start))
+    }
+}
+
+/// split indexed triangles (as returned by [`super::index`]) into chunks
+/// whose buffers are at most `max_buffer_bytes` large, keeping the order of triangles.
+/// the whole mesh is a single chunk (without copying) if it fits.
+/// at most [`super::estimate::MAX_INDICES`] indices, like [`super::index`]
+pub fn split(vertices: Vec<Vertex>, indices: Vec<u32>, max_buffer_bytes: u64) -> Vec<Chunk> {
+    let max_bytes = usize::try_from(max_buffer_bytes).unwrap_or(usize::MAX);
+    let max_vertices = (max_bytes / size_of::<BufferVertex>()).max(3);
+    // whole triangles
+    let max_indices = (max_bytes / size_of::<u32>() / 3).max(1) * 3;
+
+    if vertices.len() <= max_vertices && indices.len() <= max_indices {
+        let range = 0..indices.len().try_into().unwrap();
+        return vec![Chunk { vertices, indices, range }];
+    }
+
+    log::debug!("splitting mesh into chunks");
+    // index in the current chunk for every vertex of the whole mesh,
+    // valid if the vertex was last used in the current chunk
+    let mut local = vec![0_u32; vertices.len()];
+    let mut last_chunk = vec![usize::MAX; vertices.len()];
+
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut chunk = Chunk { vertices: Vec::new(), indices: Vec::new(), range: 0..0 };
+    for (triangle, global) in indices.chunks_exact(3).enumerate() {
+        let new_vertices = global.iter()
+            .filter(|&&i| last_chunk[i as usize] != chunks.len())
+            .count();
+        if chunk.vertices.len() + new_vertices > max_vertices || chunk.indices.len() + 3 > max_indices {
+            let start = to_index(3 * triangle);
+            chunk.range.end = start;
+            chunks.push(std::mem::replace(&mut chunk, Chunk { vertices: Vec::new(), indices: Vec::new(), range: start..start }));
+        }
+
+        for &i in global {
+            let i = i as usize;
+            if last_chunk[i] != chunks.len() {
+                last_chunk[i] = chunks.len();
+                local[i] = to_index(chunk.vertices.len());
+                chunk.vertices.push(vertices[i]);
+            }
+            chunk.indices.push(local[i]);
+        }
+    }
+    chunk.range.end = to_index(indices.len());
+    chunks.push(chunk);
+
+    log::info!("split mesh into {} chunks, as it is too large for one buffer", chunks.len());
+    chunks
+}
+
+fn to_index(i: usize) -> u32 {
+    i.try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vertex::vec2::DVec2;
+
+    /// strip of `triangles` triangles, each sharing an edge with the previous one
+    fn strip(triangles: u32) -> (Vec<Vertex>, Vec<u32>) {
+        let vertices = (0..triangles + 2)
+            .map(|i| Vertex::new(DVec2::new(f64::from(i / 2), f64::from(i % 2)), 0))
+            .collect();
+        let indices = (0..triangles).flat_map(|i| [i, i + 1, i + 2]).collect();
+        (vertices, indices)
+    }
+
+    #[test]
+    fn split_keeps_triangles_in_order() {
+        let (vertices, indices) = strip(100);
+        // at most 10 vertices or 30 indices per chunk
+        let max_buffer_bytes = 10 * size_of::<BufferVertex>() as u64;
+        let chunks = split(vertices.clone(), indices.clone(), max_buffer_bytes);
+        assert!(chunks.len() > 1);
+
+        let mut end = 0;
+        for chunk in &chunks {
+            assert_eq!(chunk.range.start, end);
+            end = chunk.range.end;
+            assert_eq!(chunk.indices.len(), (chunk.range.end - chunk.range.start) as usize);
+            assert!(chunk.vertices.len() <= 10 && chunk.indices.len() <= 30);
+            assert_eq!(chunk.indices.len() % 3, 0);
+
+            let global = &indices[chunk.range.start as usize..chunk.range.end as usize];
+            for (&local, &global) in chunk.indices.iter().zip(global) {
+                assert_eq!(chunk.vertices[local as usize], vertices[global as usize]);
+            }
+        }
+        assert_eq!(end as usize, indices.len());
+
+        let range = 3..indices.len().try_into().unwrap();
+        let parts = chunks.iter().filter_map(|chunk| chunk.local(&range)).collect::<Vec<_>>();
+        assert_eq!(parts.len(), chunks.len());
+        assert_eq!(parts[0], 3..chunks[0].range.end);
+        assert_eq!(parts.iter().map(|part| part.end - part.start).sum::<u32>() as usize, indices.len() - 3);
+    }
+
+    #[test]
+    fn split_without_limit_is_one_chunk() {
+        let (vertices, indices) = strip(100);
+        let chunks = split(vertices.clone(), indices.clone(), u64::MAX);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].range, 0..300);
+        assert_eq!((&chunks[0].vertices, &chunks[0].indices), (&vertices, &indices));
+    }
+}
@@ -0,0 +1,128 @@
+use std::{fmt, mem::size_of};
+
+use super::{BufferVertex, Vertex, VertexFormat, instanced::{BufferInstance, Instance}, stroke::Stroke};
+
+/// most indices (and vertices indexed by them) of a mesh, as they are `u32`
+pub const MAX_INDICES: usize = u32::MAX as usize;
+
+/// expected size of representing vertices as indexed triangles, without computing anything.
+/// sizes are upper bounds, as indexing usually removes a lot of duplicate vertices.
+#[derive(Clone, Copy, Debug)]
+pub struct Estimate {
+    /// before representing lines as triangles
+    pub vertices: usize,
+    pub triangles: usize,
+    pub vertex_buffer_bytes: usize,
+    pub index_buffer_bytes: usize,
+    /// copies of the vertices drawn, 1 without instancing
+    pub instances: usize,
+    pub instance_buffer_bytes: usize,
+    /// rough peak memory usage while computing, including `stored_bytes`
+    pub memory_bytes: usize,
+}
+
+impl Estimate {
+    /// `vertices`: of the iteration, as returned by [`crate::curves::Curve::vertices`] <br>
+    /// `stored_bytes`: memory in use for other iterations <br>
+    /// `stroke`: only used for lines
+    pub const fn new(vertex_format: VertexFormat, vertices: usize, stored_bytes: usize, stroke: &Stroke) -> Self {
+        // see lines_as_triangles and points_as_triangles
+        let triangles = match vertex_format {
+            VertexFormat::Lines => (vertices / 2).saturating_mul(stroke.max_triangles_per_line()),
+            VertexFormat::Triangles => vertices / 3,
+            VertexFormat::Points => vertices.saturating_mul(2),
+        };
+        let triangle_vertices = triangles.saturating_mul(3);
+        // see Stroke::triangles: ends of lines sorted by position, other end at the same position
+        let stroke_bytes = match vertex_format {
+            VertexFormat::Lines => vertices.saturating_mul(size_of::<((u64, u64), usize)>() + size_of::<Option<usize>>()),
+            VertexFormat::Triangles | VertexFormat::Points => 0,
+        };
+
+        // see lod::simplify: copy of the vertices, ends of lines ordered
+        // by position and pixels containing small details
+        let lod_bytes = vertices.saturating_mul(
+            size_of::<Vertex>() + size_of::<((u64, u64), usize)>() + size_of::<usize>() + size_of::<(i64, i64)>()
+        );
+        let vertex_bytes = triangle_vertices.saturating_mul(size_of::<Vertex>());
+        let vertex_buffer_bytes = triangle_vertices.saturating_mul(size_of::<BufferVertex>());
+        let index_bytes = triangle_vertices.saturating_mul(size_of::<u32>());
+
+        // see index: vertices as triangles, a copy of them (as bits), sorted
+        // unique vertices and a map from unique vertices to indices
+        let indexing_bytes = vertex_bytes.saturating_mul(3)
+            .saturating_add(triangle_vertices.saturating_mul(size_of::<Vertex>() + size_of::<u32>()))
+            .saturating_add(index_bytes);
+        // see tiles::Quadtree::new: triangles with their position in z-order, sorted
+        let tile_bytes = triangles.saturating_mul(size_of::<(u32, [u32; 3])>() + size_of::<u32>());
+
+        Self {
+            vertices,
+            triangles,
+            vertex_buffer_bytes,
+            index_buffer_bytes: index_bytes,
+            instances: 1,
+            instance_buffer_bytes: size_of::<BufferInstance>(),
+            memory_bytes: stored_bytes
+                .saturating_add(vertices.saturating_mul(size_of::<Vertex>()))
+                .saturating_add(lod_bytes)
+                .saturating_add(stroke_bytes)
+                .saturating_add(indexing_bytes)
+                .saturating_add(tile_bytes)
+                // vertices for the buffer are converted from the unique ones
+                .saturating_add(vertex_buffer_bytes),
+        }
+    }
+
+    /// `base_vertices`: counterclockwise triangles copied `instances` times,
+    /// see [`super::instanced::Instancing`]
+    pub const fn instanced(base_vertices: usize, instances: usize) -> Self {
+        let base = Self::new(VertexFormat::Triangles, base_vertices, 0, &Stroke::DEFAULT);
+        let instance_bytes = instances.saturating_mul(size_of::<Instance>());
+        let instance_buffer_bytes = instances.saturating_mul(size_of::<BufferInstance>());
+
+        Self {
+            vertices: base_vertices.saturating_mul(instances),
+            triangles: base.triangles.saturating_mul(instances),
+            instances,
+            instance_buffer_bytes,
+            // instances of the last iteration, the ones being computed from them
+            // and the ones converted for the buffer
+            memory_bytes: base.memory_bytes
+                .saturating_add(instance_bytes.saturating_mul(2))
+                .saturating_add(instance_buffer_bytes),
+            ..base
+        }
+    }
+
+    /// of the triangles (of one instance), an upper bound for the number of distinct vertices.
+    /// iterations with more than [`MAX_INDICES`] cannot be indexed
+    pub const fn indices(&self) -> usize {
+        self.index_buffer_bytes / size_of::<u32>()
+    }
+
+    /// largest of the buffers
+    pub const fn max_buffer_bytes(&self) -> usize {
+        let max = if self.vertex_buffer_bytes > self.index_buffer_bytes {
+            self.vertex_buffer_bytes
+        } else {
+            self.index_buffer_bytes
+        };
+        if self.instance_buffer_bytes > max { self.instance_buffer_bytes } else { max }
+    }
+}
+
+impl fmt::Display for Estimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} vertices, {} triangles", self.vertices, self.triangles)?;
+        if self.instances > 1 {
+            write!(f, " in {} instances", self.instances)?;
+        }
+        write!(f, ", {} MiB of memory, {} MiB largest buffer", mib(self.memory_bytes), mib(self.max_buffer_bytes()))
+    }
+}
+
+/// bytes to mebibytes, rounded up
+pub const fn mib(bytes: usize) -> usize {
+    bytes.div_ceil(1 << 20)
+}
@@ -0,0 +1,166 @@
+//! fractals as copies of one base mesh, so that memory grows with the number of copies
+//! instead of copies times base vertices. every copy is drawn as one instance.
+
+use rayon::prelude::*;
+
+use super::{Vertex, affine::Affine, estimate::Estimate, vec2::{DVec2, Vec2}};
+
+/// transform of one copy of the base mesh
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Instance {
+    pub affine: Affine,
+    /// added to the iteration of the base vertices
+    pub iteration: u32,
+}
+
+impl Instance {
+    /// base mesh as it is
+    pub const IDENTITY: Self = Self::new(Affine::scale(1., DVec2::new(0., 0.)), 0);
+
+    pub const fn new(affine: Affine, iteration: u32) -> Self {
+        Self { affine, iteration }
+    }
+
+    /// vertex of the base mesh as part of this copy, like the vertex shader does
+    pub fn apply(&self, vertex: Vertex) -> Vertex {
+        Vertex::new(self.affine.apply(vertex.position), vertex.iteration + self.iteration)
+    }
+}
+
+// follow C's rules for the memory layout (e.g. dont reorder)
+#[repr(C)]
+#[derive(
+    Clone, Copy, PartialEq, Debug,
+    // allow bitwise casts with bytemuck
+    bytemuck::Zeroable, bytemuck::Pod,
+)]
+/// [`Instance`] to store in the instance buffer, with single precision
+/// and (like [`super::BufferVertex`]) relative to an origin close to the camera
+pub struct BufferInstance {
+    /// what the x axis is mapped to (first column of the linear transformation)
+    pub x_axis: Vec2,
+    /// what the y axis is mapped to (second column of the linear transformation)
+    pub y_axis: Vec2,
+    pub offset: Vec2,
+    pub iteration: u32,
+}
+
+#[cfg(feature = "gui")]
+impl BufferInstance {
+    /// shape of each instance for the buffer, following [`super::BufferVertex::buffer_layout`]
+    const ATTRIBUTES: [wgpu::VertexAttribute; 4] =
+        // map shader locations to the data types
+        wgpu::vertex_attr_array![2 => Float32x2, 3 => Float32x2, 4 => Float32x2, 5 => Uint32];
+
+    pub const fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+impl BufferInstance {
+    pub fn new(instance: &Instance, origin: DVec2) -> Self {
+        let [[a, b], [c, d]] = instance.affine.matrix;
+        Self {
+            x_axis: DVec2::new(a, c).to_f32(),
+            y_axis: DVec2::new(b, d).to_f32(),
+            offset: (instance.affine.offset - origin).to_f32(),
+            iteration: instance.iteration,
+        }
+    }
+}
+
+/// `instances` to store in the instance buffer, relative to `origin`
+pub fn to_buffer(instances: &[Instance], origin: DVec2) -> Vec<BufferInstance> {
+    instances.par_iter()
+        .map(|instance| BufferInstance::new(instance, origin))
+        .collect()
+}
+
+/// self-similar fractal whose iterations map the previous iteration through each transform,
+/// optionally keeping the base mesh (see [`crate::curves::self_similar::SelfSimilar`])
+#[derive(Clone, Debug)]
+pub struct Instancing {
+    /// counterclockwise triangles
+    pub base: Vec<Vertex>,
+    /// should not mirror, as mirrored copies of triangles are
+    /// clockwise and therefore culled when drawn as instances
+    pub transforms: Vec<Affine>,
+    pub keep_base: bool,
+}
+
+impl Instancing {
+    /// number of copies of the base mesh in `iteration` (iteration 0 meaning initial state)
+    pub fn instance_count(&self, iteration: usize) -> usize {
+        let transforms = self.transforms.len();
+        let pow = |exponent: usize| transforms.saturating_pow(exponent.try_into().unwrap_or(u32::MAX));
+
+        if !self.keep_base {
+            pow(iteration)
+        } else if transforms <= 1 {
+            transforms.saturating_mul(iteration) + 1
+        } else {
+            (pow(iteration.saturating_add(1)) - 1) / (transforms - 1)
+        }
+    }
+
+    /// expected size of `iteration` drawn as instances
+    pub fn estimate(&self, iteration: usize) -> Estimate {
+        Estimate::instanced(self.base.len(), self.instance_count(iteration))
+    }
+
+    /// copies of the base mesh making up `iteration`: the base (if kept), followed by one
+    /// whole copy of the last iteration per transform. flattened, this is the order of
+    /// [`crate::curves::self_similar::SelfSimilar`] computed without instancing, and of
+    /// subdividing every triangle into one child per transform (e.g. the sierpinski triangle)
+    pub fn instanced(&self, iteration: usize) -> Instanced {
+        let mut instances = vec![Instance::IDENTITY];
+
+        for _ in 0..iteration {
+            let base = if self.keep_base { instances.first().copied() } else { None };
+
+            // one whole copy of the last iteration after the other
+            let copies = self.transforms.par_iter().flat_map(|transform| {
+                instances.par_iter().map(move |instance| {
+                    // copies are one iteration deeper
+                    Instance::new(transform.after(&instance.affine), instance.iteration + 1)
+                })
+            });
+            instances = base.into_par_iter().chain(copies).collect();
+        }
+
+        Instanced { base: self.base.clone(), instances }
+    }
+}
+
+/// copies of a base mesh, drawn with one instanced draw call
+#[derive(Clone, Debug)]
+pub struct Instanced {
+    /// counterclockwise triangles
+    pub base: Vec<Vertex>,
+    pub instances: Vec<Instance>,
+}
+
+impl Instanced {
+    /// highest iteration value present in the copies of the base mesh
+    pub fn max_iteration(&self) -> u32 {
+        let base = self.base.iter().map(|v| v.iteration).max().unwrap_or_default();
+        let instances = self.instances.par_iter().map(|i| i.iteration).max().unwrap_or_default();
+        base + instances
+    }
+
+    /// every copy of the base mesh as plain vertices (triangles),
+    /// e.g. for exporting without instancing
+    pub fn flatten(&self) -> Vec<Vertex> {
+        self.instances.par_iter().flat_map_iter(|instance| {
+            // keep triangles counterclockwise when mirrored
+            let mirrored = instance.affine.determinant() < 0.;
+            self.base.chunks_exact(3).flat_map(move |triangle| {
+                (0..3).map(move |i| instance.apply(triangle[if mirrored { 2 - i } else { i }]))
+            })
+        }).collect()
+    }
+}
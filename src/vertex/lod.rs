@@ -0,0 +1,224 @@
+//! level of detail: geometry smaller than a pixel is merged or dropped
+//! before representing it as triangles, as it would not be visible anyway
+
+use rayon::prelude::*;
+use std::collections::HashSet;
+
+use super::{Vertex, VertexFormat, vec2::DVec2};
+
+/// size of one pixel in the coordinates of vertices, for an image/window
+/// of `width`x`height` pixels with the camera zoomed in by `zoom`
+/// (the smaller side always spans from -1 to 1 when not zoomed)
+pub fn pixel_size(width: u32, height: u32, zoom: f64) -> f64 {
+    2. / (f64::from(width.min(height).max(1)) * zoom)
+}
+
+/// `vertices` (as returned by [`crate::curves::Curve::vertices`]) with details
+/// smaller than `pixel_size` merged or dropped. also returns the number of
+/// lines/triangles/points that were removed. <br>
+/// lines: paths of connected lines (in any order) are merged into lines
+/// spanning at least a pixel, so vertices move by less than a pixel. <br>
+/// paths, triangles and points that are still smaller than a pixel:
+/// only the first one in each pixel is kept
+pub fn simplify(vertices: &[Vertex], vertex_format: VertexFormat, pixel_size: f64) -> (Vec<Vertex>, usize) {
+    log::debug!("merging and dropping details smaller than a pixel");
+    let group_size = vertex_format.group_size();
+    let mut pixels = Pixels::new(pixel_size);
+
+    let simplified = match vertex_format {
+        VertexFormat::Lines => simplify_lines(vertices, &mut pixels),
+        VertexFormat::Triangles => vertices.chunks_exact(3)
+            .filter(|triangle| {
+                let [a, b, c] = [0, 1, 2].map(|i| triangle[i].position);
+                let small = [b - a, c - b, a - c].iter().all(|edge| edge.len() < pixel_size);
+                !small || pixels.claim((a + b + c) / 3.)
+            })
+            .flatten()
+            .copied()
+            .collect(),
+        VertexFormat::Points => vertices.iter()
+            .filter(|point| pixels.claim(point.position))
+            .copied()
+            .collect::<Vec<_>>(),
+    };
+
+    let culled = (vertices.len() - simplified.len()) / group_size;
+    log::info!("culled {culled} of {} details smaller than a pixel", vertices.len() / group_size);
+    (simplified, culled)
+}
+
+fn simplify_lines(vertices: &[Vertex], pixels: &mut Pixels) -> Vec<Vertex> {
+    let ends = Ends::new(vertices);
+    let mut used = vec![false; vertices.len() / 2];
+    let mut simplified = Vec::with_capacity(vertices.len());
+
+    for line in 0..used.len() {
+        if used[line] {
+            continue;
+        }
+        used[line] = true;
+        // follow connected lines in both directions
+        let mut path = ends.follow(2 * line, &mut used);
+        path.reverse();
+        path.extend(ends.follow(2 * line + 1, &mut used));
+        simplify_path(&path, pixels, &mut simplified);
+    }
+
+    simplified
+}
+
+/// add `path` (connected vertices) as lines spanning at least a pixel,
+/// so that vertices move by less than a pixel. a path spanning
+/// less than a pixel is only kept if it is the first in its pixel
+fn simplify_path(path: &[Vertex], pixels: &mut Pixels, simplified: &mut Vec<Vertex>) {
+    let first_line = simplified.len();
+    let mut start = path[0];
+    for &vertex in &path[1..] {
+        if (vertex.position - start.position).len() >= pixels.size {
+            simplified.extend([start, vertex]);
+            start = vertex;
+        }
+    }
+
+    let last = path[path.len() - 1];
+    if start.position == last.position {
+        return;
+    }
+    if simplified.len() > first_line {
+        // extend the last line to the end of the path instead
+        *simplified.last_mut().unwrap() = last;
+    } else if pixels.claim(start.position) {
+        simplified.extend([start, last]);
+    }
+}
+
+/// ends of lines (indices of vertices) ordered by position
+struct Ends<'a> {
+    vertices: &'a [Vertex],
+    /// see index for working with bits
+    sorted: Vec<((u64, u64), usize)>,
+    /// index in `sorted` for every end
+    slots: Vec<usize>,
+}
+
+impl<'a> Ends<'a> {
+    fn new(vertices: &'a [Vertex]) -> Self {
+        let mut sorted = vertices.par_iter()
+            .enumerate()
+            .map(|(i, v)| ((v.position.x.to_bits(), v.position.y.to_bits()), i))
+            .collect::<Vec<_>>();
+        sorted.par_sort_unstable();
+
+        let mut slots = vec![0; vertices.len()];
+        for (slot, &(_, end)) in sorted.iter().enumerate() {
+            slots[end] = slot;
+        }
+        Self { vertices, sorted, slots }
+    }
+
+    /// vertices of a path of unused lines connected to the line of `end`,
+    /// starting at `end`. lines of the path are marked as used
+    fn follow(&self, mut end: usize, used: &mut [bool]) -> Vec<Vertex> {
+        let mut path = vec![end];
+        while let Some(next) = self.unused_at(end, used) {
+            used[next / 2] = true;
+            // leave the line at its other end
+            end = next ^ 1;
+            path.push(end);
+        }
+        path.into_iter().map(|end| self.vertices[end]).collect()
+    }
+
+    /// end of an unused line at the same position as `end`
+    fn unused_at(&self, end: usize, used: &[bool]) -> Option<usize> {
+        let slot = self.slots[end];
+        let position = self.sorted[slot].0;
+        let same = |&&(other, _): &&((u64, u64), usize)| other == position;
+        let before = self.sorted[..slot].iter().rev().take_while(same);
+        let after = self.sorted[slot + 1..].iter().take_while(same);
+        before.chain(after)
+            .map(|&(_, other)| other)
+            .find(|other| !used[other / 2])
+    }
+}
+
+/// pixels already containing details smaller than a pixel
+struct Pixels {
+    size: f64,
+    claimed: HashSet<(i64, i64)>,
+}
+
+impl Pixels {
+    fn new(size: f64) -> Self {
+        Self { size, claimed: HashSet::new() }
+    }
+
+    /// `true` if the pixel at `position` was not claimed yet
+    #[allow(clippy::cast_possible_truncation)]
+    fn claim(&mut self, position: DVec2) -> bool {
+        let pixel = ((position.x / self.size).floor() as i64, (position.y / self.size).floor() as i64);
+        self.claimed.insert(pixel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(x: f64, y: f64) -> Vertex {
+        Vertex::new(DVec2::new(x, y), 0)
+    }
+
+    #[test]
+    fn short_lines_are_merged_or_dropped() {
+        // path from (0, 0) to (2.5, 0) made of 10 lines, in any order and direction
+        let point = |i: u32| vertex(f64::from(i) / 4., 0.);
+        let mut vertices = vec![point(0), point(1)];
+        for i in [6, 2, 9, 4, 1, 8, 3, 7, 5] {
+            let line = [point(i), point(i + 1)];
+            vertices.extend(if i % 2 == 0 { line } else { [line[1], line[0]] });
+        }
+        // two lines within the same pixel
+        vertices.extend([vertex(5.1, 5.1), vertex(5.2, 5.1), vertex(5.3, 5.3), vertex(5.4, 5.3)]);
+        let large = [vertex(10., 0.), vertex(20., 0.)];
+        vertices.extend(large);
+
+        let (simplified, culled) = simplify(&vertices, VertexFormat::Lines, 1.);
+        assert_eq!(simplified, [
+            // no longer shorter than a pixel, ending at the same positions
+            point(0), point(4), point(4), point(10),
+            vertex(5.1, 5.1), vertex(5.2, 5.1),
+            large[0], large[1],
+        ]);
+        assert_eq!(culled, 13 - 4);
+    }
+
+    #[test]
+    fn small_triangles_and_points_are_dropped() {
+        let large = [vertex(0., 0.), vertex(4., 0.), vertex(0., 4.)];
+        let small = [vertex(2.1, 2.1), vertex(2.3, 2.1), vertex(2.1, 2.3)];
+        let mut vertices = large.to_vec();
+        vertices.extend(small);
+        // same pixel as the previous one
+        vertices.extend(small.map(|v| vertex(v.position.x + 0.5, v.position.y + 0.5)));
+        vertices.extend(large);
+        let (simplified, culled) = simplify(&vertices, VertexFormat::Triangles, 1.);
+        assert_eq!(simplified, [large, small, large].concat());
+        assert_eq!(culled, 1);
+
+        let points = [vertex(0.1, 0.1), vertex(0.9, 0.9), vertex(1.1, 0.1), vertex(-0.1, 0.1)];
+        let (simplified, culled) = simplify(&points, VertexFormat::Points, 1.);
+        assert_eq!(simplified, [points[0], points[2], points[3]]);
+        assert_eq!(culled, 1);
+    }
+
+    #[test]
+    fn large_geometry_is_untouched() {
+        let lines = [(0., 0.), (3., 0.), (3., 0.), (3., 3.), (5., 5.), (7., 7.)].map(|(x, y)| vertex(x, y));
+        assert_eq!(simplify(&lines, VertexFormat::Lines, 1.), (lines.to_vec(), 0));
+        assert_eq!(simplify(&lines, VertexFormat::Triangles, 1.), (lines.to_vec(), 0));
+        // only the second point at (3, 0) shares a pixel
+        let points = [&lines[..2], &lines[3..]].concat();
+        assert_eq!(simplify(&lines, VertexFormat::Points, 0.5), (points, 1));
+    }
+}
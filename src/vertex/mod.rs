@@ -0,0 +1,275 @@
+pub mod affine;
+pub mod chunks;
+pub mod estimate;
+pub mod instanced;
+pub mod lod;
+pub mod stroke;
+pub mod tiles;
+pub mod vec2;
+
+use rayon::prelude::*;
+
+use stroke::Stroke;
+use vec2::{DVec2, Vec2};
+
+/// for representing points as triangles
+pub const POINT_SIZE: f64 = 0.004;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+/// a vertex of fractal geometry, see [`BufferVertex`] for the vertex buffer
+pub struct Vertex {
+    pub position: DVec2,
+    /// fractal iteration this vertex was created in,
+    /// 0 meaning the initial state
+    pub iteration: u32,
+}
+
+impl Vertex {
+    pub const fn new(position: DVec2, iteration: u32) -> Self {
+        Self { position, iteration }
+    }
+}
+
+// follow C's rules for the memory layout (e.g. dont reorder)
+#[repr(C)]
+#[derive(
+    Clone, Copy, PartialEq, Debug,
+    // allow bitwise casts with bytemuck
+    bytemuck::Zeroable, bytemuck::Pod,
+)]
+/// a vertex to store in the vertex buffer, with single precision.
+/// positions are relative to an origin close to the camera,
+/// so they stay precise enough when zooming in far
+pub struct BufferVertex {
+    pub position: Vec2,
+    // need 32 bits to avoid padding and have a corresponding type in WGSL,
+    // otherwise u8 would have had enough possible values
+    pub iteration: u32,
+}
+
+#[cfg(feature = "gui")]
+impl BufferVertex {
+    /// shape of each vertex for the buffer
+    const ATTRIBUTES: [wgpu::VertexAttribute; 2] =
+        // map shader locations to the data types
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Uint32];
+
+    pub const fn buffer_layout() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+impl BufferVertex {
+    pub fn new(vertex: &Vertex, origin: DVec2) -> Self {
+        Self {
+            position: (vertex.position - origin).to_f32(),
+            iteration: vertex.iteration,
+        }
+    }
+}
+
+/// `vertices` to store in the vertex buffer, relative to `origin`
+pub fn to_buffer(vertices: &[Vertex], origin: DVec2) -> Vec<BufferVertex> {
+    vertices.par_iter()
+        .map(|vertex| BufferVertex::new(vertex, origin))
+        .collect()
+}
+
+
+/// describes how an array of vertices should be interpreted
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VertexFormat {
+    /// groups of two to form lines
+    Lines,
+    /// groups of three to form triangles
+    Triangles,
+    /// every vertex on its own
+    Points,
+}
+
+impl VertexFormat {
+    /// number of vertices forming one line/triangle
+    pub const fn group_size(self) -> usize {
+        match self {
+            Self::Lines => 2,
+            Self::Triangles => 3,
+            Self::Points => 1,
+        }
+    }
+}
+
+/// transform ordered, partly duplicate vertices into unique vertices and indices.
+/// vertices are only the same if they are with double precision.
+/// at most [`estimate::MAX_INDICES`] vertices, check [`estimate::Estimate::indices`] first
+pub fn index(vertices: &[Vertex]) -> (Vec<Vertex>, Vec<u32>) {
+    // efficient handling of data is a lot simpler when you can e.g.
+    // hash, order or compare it. rusts floating point primitives
+    // (that are part of each Vertex) have some difficulties with that.
+    // solution: map vertices to their bits, work with that,
+    //           and finally map back
+    let to_bits = |v: &Vertex| (v.position.x.to_bits(), v.position.y.to_bits(), v.iteration);
+    let from_bits = |&(x, y, iteration): &(u64, u64, u32)|
+        Vertex::new(DVec2::new(f64::from_bits(x), f64::from_bits(y)), iteration);
+
+    let bit_vertices = vertices.par_iter().map(to_bits).collect::<Vec<_>>();
+
+    log::debug!("determining unique vertices");
+    let mut unique_bit_vertices = bit_vertices.clone();
+    unique_bit_vertices.par_sort_unstable();
+    unique_bit_vertices.dedup();
+
+    let unique_vertices = unique_bit_vertices.par_iter().map(from_bits).collect();
+
+    // for O(1) lookups when building indices from vertices
+    log::debug!("building vertex-index-map");
+    let vertex_index_map = unique_bit_vertices.par_iter()
+        .enumerate()
+        .map(|t| (t.1, t.0.try_into().unwrap()))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    log::debug!("determining indices");
+    let indices = bit_vertices.par_iter()
+        .map(|v| *vertex_index_map.get(&v).unwrap())
+        .collect::<Vec<_>>();
+
+    log::info!("determined {} triangles to render", indices.len() / 3);
+    (unique_vertices, indices)
+}
+
+/// like [`index`], but first merges vertices closer than `tolerance` (see [`weld`]).
+/// also returns the number of merged vertices
+pub fn index_welded(vertices: &[Vertex], tolerance: f64) -> (Vec<Vertex>, Vec<u32>, usize) {
+    let (welded, merged) = weld(vertices, tolerance);
+    log::info!("merged {merged} nearly coincident vertices");
+    let (unique_vertices, indices) = index(&welded);
+    (unique_vertices, indices, merged)
+}
+
+/// move vertices to the position of an earlier vertex of the same iteration
+/// that is at most `tolerance` away, so that differences from floating point
+/// rounding do not prevent [`index`] from sharing them.
+/// also returns the number of distinct vertices that were merged into others
+pub fn weld(vertices: &[Vertex], tolerance: f64) -> (Vec<Vertex>, usize) {
+    if tolerance <= 0. || tolerance.is_nan() {
+        return (vertices.to_vec(), 0);
+    }
+
+    // see index for working with bits
+    let to_bits = |v: &Vertex| (v.position.x.to_bits(), v.position.y.to_bits(), v.iteration);
+
+    log::debug!("determining unique vertices to weld");
+    let mut unique_bit_vertices = vertices.par_iter().map(to_bits).collect::<Vec<_>>();
+    unique_bit_vertices.par_sort_unstable();
+    unique_bit_vertices.dedup();
+
+    // spatial hashing: vertices within tolerance of each other
+    // are at most one grid cell of size tolerance apart
+    #[allow(clippy::cast_possible_truncation)]
+    let cell = |position: DVec2| ((position.x / tolerance).floor() as i64, (position.y / tolerance).floor() as i64);
+
+    log::debug!("welding vertices");
+    let mut grid = std::collections::HashMap::<_, Vec<DVec2>>::new();
+    let mut welded_positions = std::collections::HashMap::with_capacity(unique_bit_vertices.len());
+    let mut merged = 0;
+    for &(x, y, iteration) in &unique_bit_vertices {
+        let position = DVec2::new(f64::from_bits(x), f64::from_bits(y));
+        let (cell_x, cell_y) = cell(position);
+
+        let nearby = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (cell_x + dx, cell_y + dy, iteration)))
+            .filter_map(|key| grid.get(&key))
+            .flatten()
+            .find(|&&other| (other - position).len() <= tolerance)
+            .copied();
+
+        let welded = nearby.unwrap_or_else(|| {
+            grid.entry((cell_x, cell_y, iteration)).or_default().push(position);
+            position
+        });
+        if nearby.is_some() {
+            merged += 1;
+        }
+        welded_positions.insert((x, y, iteration), welded);
+    }
+
+    let welded_vertices = vertices.par_iter()
+        .map(|v| Vertex::new(welded_positions[&to_bits(v)], v.iteration))
+        .collect();
+
+    (welded_vertices, merged)
+}
+
+/// see [`Stroke::triangles`]
+pub fn lines_as_triangles(vertices: &[Vertex], stroke: &Stroke) -> Vec<Vertex> {
+    log::debug!("representing lines as triangles");
+    stroke.triangles(vertices)
+}
+
+pub fn points_as_triangles(vertices: &[Vertex], point_size: f64) -> Vec<Vertex> {
+    log::debug!("representing points as triangles");
+    vertices.par_iter().map(|point| {
+        let (position, iteration) = (point.position, point.iteration);
+        let half = point_size / 2.;
+
+        let bottom_left  = Vertex::new(position + DVec2::new(-half, -half), iteration);
+        let bottom_right = Vertex::new(position + DVec2::new( half, -half), iteration);
+        let top_left     = Vertex::new(position + DVec2::new(-half,  half), iteration);
+        let top_right    = Vertex::new(position + DVec2::new( half,  half), iteration);
+
+        [
+            // point as square of two (counterclockwise) triangles
+            bottom_left, bottom_right, top_right,
+            bottom_left, top_right,    top_left,
+        ]
+    }).flatten().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::curves::{Curve, koch_snowflake::KochSnowflake};
+
+    #[test]
+    fn koch_snowflake_endpoints_are_shared() {
+        let mut curve = KochSnowflake::new();
+        for iteration in 0..5 {
+            let vertices = curve.vertices(iteration).clone();
+            let (unique_vertices, indices, _) = index_welded(&vertices, 1e-9);
+
+            // closed path: every endpoint is shared by two lines
+            let lines = 3 * 4_usize.pow(iteration.try_into().unwrap());
+            assert_eq!(vertices.len(), 2 * lines);
+            assert_eq!(unique_vertices.len(), lines, "iteration {iteration}");
+            assert_eq!(indices.len(), vertices.len());
+            for (vertex, &index) in vertices.iter().zip(&indices) {
+                let unique = unique_vertices[index as usize];
+                assert!((unique.position - vertex.position).len() <= 1e-9);
+                assert_eq!(unique.iteration, vertex.iteration);
+            }
+        }
+    }
+
+    #[test]
+    fn different_iterations_stay_distinct() {
+        let position = DVec2::new(0.25, -0.5);
+        let nearby = DVec2::new(0.25 + 1e-12, -0.5);
+        let vertices = [
+            Vertex::new(position, 0), Vertex::new(position, 1),
+            Vertex::new(nearby, 0), Vertex::new(nearby, 1),
+        ];
+
+        let (unique_vertices, indices) = index(&vertices);
+        assert_eq!(unique_vertices.len(), 4);
+
+        let (unique_vertices, indices_welded, merged) = index_welded(&vertices, 1e-9);
+        assert_eq!(merged, 2);
+        assert_eq!(unique_vertices.len(), 2);
+        assert_eq!(indices_welded[0], indices_welded[2]);
+        assert_eq!(indices_welded[1], indices_welded[3]);
+        assert_ne!(indices_welded[0], indices_welded[1]);
+        assert_ne!(indices[0], indices[1]);
+    }
+}
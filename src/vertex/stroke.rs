@@ -0,0 +1,361 @@
+//! lines with a width, as triangles: joins where exactly two lines meet,
+//! caps at all other ends of lines
+
+use rayon::prelude::*;
+use std::f64::consts::PI;
+
+use super::{Vertex, vec2::DVec2};
+
+/// triangles forming half a circle, see [`Join::Round`] and [`Cap::Round`]
+const ROUND_SEGMENTS: usize = 8;
+/// max. deviation of the cosine of the angle between two lines from ±1
+/// to still count as continuing straight/turning back
+const STRAIGHT_TOLERANCE: f64 = 1e-9;
+
+/// how the outer corners of two connected lines are filled
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Join {
+    /// extend the outer edges until they meet, see [`Stroke::miter_limit`]
+    Miter,
+    /// connect the outer corners directly
+    Bevel,
+    /// circular arc around the shared vertex
+    Round,
+}
+
+/// how ends of lines that are not joined look
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cap {
+    /// end exactly at the vertex
+    Butt,
+    /// extend by half the width
+    Square,
+    /// half circle around the vertex
+    Round,
+}
+
+/// how lines are represented as triangles
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Stroke {
+    /// width of lines at vertices of iteration 0
+    pub width: f64,
+    /// the width is multiplied by this for every iteration of a vertex,
+    /// so lines taper off towards newer vertices if less than 1
+    pub falloff: f64,
+    /// lower bound for the falloff, so that lines stay visible
+    /// (limited to `width`)
+    pub min_width: f64,
+    /// where exactly two lines share a vertex
+    pub join: Join,
+    /// max. ratio of the length of a miter to the width, beyond which
+    /// [`Join::Miter`] falls back to [`Join::Bevel`] (like in svg)
+    pub miter_limit: f64,
+    /// at all other ends of lines
+    pub cap: Cap,
+}
+
+impl Default for Stroke {
+    fn default() -> Self { Self::DEFAULT }
+}
+
+impl Stroke {
+    pub const DEFAULT: Self = Self {
+        width: 0.005,
+        falloff: 1.,
+        min_width: 0.,
+        join: Join::Miter,
+        miter_limit: 4.,
+        cap: Cap::Butt,
+    };
+
+    /// width of lines at vertices of `iteration`
+    pub fn width(&self, iteration: u32) -> f64 {
+        let width = self.width * self.falloff.powi(iteration.try_into().unwrap_or(i32::MAX));
+        width.max(self.min_width.min(self.width))
+    }
+
+    /// upper bound of triangles representing one line, see [`Self::triangles`]
+    pub const fn max_triangles_per_line(&self) -> usize {
+        // half of a join is done by each of the two lines
+        let join = match self.join {
+            Join::Miter | Join::Bevel => 1,
+            Join::Round => ROUND_SEGMENTS / 2,
+        };
+        let cap = match self.cap {
+            Cap::Butt => 0,
+            Cap::Square => 2,
+            Cap::Round => ROUND_SEGMENTS,
+        };
+        // rectangle and both ends
+        2 + 2 * if join > cap { join } else { cap }
+    }
+
+    /// lines (two vertices each) as counterclockwise triangles. lines sharing a vertex
+    /// with exactly one other line are joined, all other ends are capped
+    pub fn triangles(&self, vertices: &[Vertex]) -> Vec<Vertex> {
+        let partners = partners(vertices);
+
+        vertices.par_chunks_exact(2).enumerate().flat_map_iter(|(line, ends)| {
+            let mut triangles = Vec::with_capacity(3 * self.max_triangles_per_line());
+            let (a, b) = (ends[0], ends[1]);
+            // without a direction there is nothing to draw
+            if a.position == b.position {
+                return triangles;
+            }
+
+            let direction = (b.position - a.position).set_len(1.);
+            let normal = DVec2::new(-direction.y, direction.x);
+            let (a_half, b_half) = (self.width(a.iteration) / 2., self.width(b.iteration) / 2.);
+
+            // line as (possibly tapered) rectangle of two triangles
+            let a_left  = Vertex::new(a.position + normal * a_half, a.iteration);
+            let a_right = Vertex::new(a.position - normal * a_half, a.iteration);
+            let b_left  = Vertex::new(b.position + normal * b_half, b.iteration);
+            let b_right = Vertex::new(b.position - normal * b_half, b.iteration);
+            triangles.extend([a_left, a_right, b_left, b_left, a_right, b_right]);
+
+            // directions pointing from the end into the line
+            for (end, inward) in [(2 * line, direction), (2 * line + 1, -direction)] {
+                let joined = partners[end]
+                    .map(|partner| (partner, partner_direction(vertices, partner)))
+                    // lines turning back on themselves are capped instead
+                    .filter(|&(_, other)| other.is_some_and(|other| dot(inward, other) < 1. - STRAIGHT_TOLERANCE));
+
+                match joined {
+                    Some((partner, Some(other))) => self.join(&mut triangles, vertices[end], inward, vertices[partner], other),
+                    _ => self.cap(&mut triangles, vertices[end], inward),
+                }
+            }
+
+            triangles
+        }).collect()
+    }
+
+    /// add the half of the join at `end` that belongs to its line.
+    /// `inward`/`other`: directions from the shared vertex into the lines
+    fn join(&self, triangles: &mut Vec<Vertex>, end: Vertex, inward: DVec2, partner: Vertex, other: DVec2) {
+        // continuing straight, rectangles already meet
+        if dot(inward, other) < -1. + STRAIGHT_TOLERANCE {
+            return;
+        }
+
+        let center = end.position;
+        let half = self.width(end.iteration) / 2.;
+        let outer = outer_normal(inward, other);
+        let corner = center + outer * half;
+        // away from both lines, halfway between them
+        let bisector = (-(inward + other)).set_len(1.);
+        let cos = dot(outer, bisector);
+
+        let mut triangle = |b: DVec2, c: DVec2| push_counterclockwise(triangles, end.iteration, [center, b, c]);
+        match self.join {
+            Join::Miter if 1. / cos <= self.miter_limit => triangle(corner, center + bisector * (half / cos)),
+            Join::Miter | Join::Bevel => {
+                // halfway to the corner of the other line, which does the other half
+                let other_corner = center + outer_normal(other, inward) * (self.width(partner.iteration) / 2.);
+                triangle(corner, (corner + other_corner) / 2.);
+            },
+            Join::Round => {
+                let angle = cos.clamp(-1., 1.).acos();
+                let turn = if cross(outer, bisector) >= 0. { 1. } else { -1. };
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let segments = ((angle / (PI / ROUND_SEGMENTS as f64)).ceil() as usize).max(1);
+                arc(&mut triangle, center, outer * half, turn * angle, segments);
+            },
+        }
+    }
+
+    /// add the cap at `end`, whose line goes in direction `inward`
+    fn cap(&self, triangles: &mut Vec<Vertex>, end: Vertex, inward: DVec2) {
+        let center = end.position;
+        let half = self.width(end.iteration) / 2.;
+        let normal = DVec2::new(-inward.y, inward.x) * half;
+        let outward = -inward * half;
+
+        match self.cap {
+            Cap::Butt => {},
+            Cap::Square => {
+                let (left, right) = (center + normal, center - normal);
+                push_counterclockwise(triangles, end.iteration, [left, right, right + outward]);
+                push_counterclockwise(triangles, end.iteration, [left, right + outward, left + outward]);
+            },
+            // from one side of the line around the end to the other
+            Cap::Round => arc(
+                &mut |b, c| push_counterclockwise(triangles, end.iteration, [center, b, c]),
+                center, normal, PI, ROUND_SEGMENTS,
+            ),
+        }
+    }
+}
+
+/// ends of other lines at the same position, if there is exactly one
+fn partners(vertices: &[Vertex]) -> Vec<Option<usize>> {
+    // see index for working with bits
+    let mut ends = vertices.par_iter()
+        .enumerate()
+        .map(|(i, v)| ((v.position.x.to_bits(), v.position.y.to_bits()), i))
+        .collect::<Vec<_>>();
+    ends.par_sort_unstable();
+
+    let mut partners = vec![None; vertices.len()];
+    for group in ends.chunk_by(|a, b| a.0 == b.0) {
+        if let &[(_, a), (_, b)] = group {
+            // both ends of the same line
+            if a / 2 != b / 2 {
+                partners[a] = Some(b);
+                partners[b] = Some(a);
+            }
+        }
+    }
+    partners
+}
+
+/// direction from the end at `index` into its line, `None` if the line has no length
+fn partner_direction(vertices: &[Vertex], index: usize) -> Option<DVec2> {
+    let (end, other) = (vertices[index].position, vertices[index ^ 1].position);
+    (end != other).then(|| (other - end).set_len(1.))
+}
+
+/// unit normal of `inward` pointing away from `other`
+fn outer_normal(inward: DVec2, other: DVec2) -> DVec2 {
+    let left = DVec2::new(-inward.y, inward.x);
+    if dot(left, other) <= 0. { left } else { -left }
+}
+
+/// triangles fanning around `center` from `center + start` by `angle` (counterclockwise if positive)
+fn arc(triangle: &mut impl FnMut(DVec2, DVec2), center: DVec2, start: DVec2, angle: f64, segments: usize) {
+    #[allow(clippy::cast_precision_loss)]
+    let step = angle / segments as f64;
+    let mut last = center + start;
+    for i in 1..=segments {
+        #[allow(clippy::cast_precision_loss)]
+        let next = center + start.rotate_ccw(step * i as f64);
+        triangle(last, next);
+        last = next;
+    }
+}
+
+fn push_counterclockwise(triangles: &mut Vec<Vertex>, iteration: u32, [a, b, c]: [DVec2; 3]) {
+    let (b, c) = if cross(b - a, c - a) >= 0. { (b, c) } else { (c, b) };
+    triangles.extend([a, b, c].map(|position| Vertex::new(position, iteration)));
+}
+
+fn dot(a: DVec2, b: DVec2) -> f64 {
+    a.x.mul_add(b.x, a.y * b.y)
+}
+
+fn cross(a: DVec2, b: DVec2) -> f64 {
+    a.x.mul_add(b.y, -(a.y * b.x))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// lines along `points`, one after the other
+    fn polyline(points: &[(f64, f64)]) -> Vec<Vertex> {
+        points.windows(2)
+            .flat_map(|line| line.iter().map(|&(x, y)| Vertex::new(DVec2::new(x, y), 0)))
+            .collect()
+    }
+
+    fn stroke(join: Join, cap: Cap) -> Stroke {
+        Stroke { width: 1., join, cap, ..Stroke::DEFAULT }
+    }
+
+    /// whether `point` is within one of the (counterclockwise) `triangles`
+    fn covers(triangles: &[Vertex], point: (f64, f64)) -> bool {
+        let point = DVec2::new(point.0, point.1);
+        triangles.chunks_exact(3).any(|triangle| {
+            (0..3).all(|i| {
+                let (a, b) = (triangle[i].position, triangle[(i + 1) % 3].position);
+                cross(b - a, point - a) >= -1e-12
+            })
+        })
+    }
+
+    /// points in the square from `min` to `max` on a grid
+    fn grid(min: (f64, f64), max: (f64, f64)) -> impl Iterator<Item = (f64, f64)> {
+        let at = |min: f64, max: f64, i: u32| min + (max - min) * f64::from(i) / 10.;
+        (0..=10).flat_map(move |i| (0..=10).map(move |j| (at(min.0, max.0, i), at(min.1, max.1, j))))
+    }
+
+    #[test]
+    fn partners_are_exactly_two_lines() {
+        let mut vertices = polyline(&[(0., 0.), (1., 0.), (1., 1.)]);
+        // isolated
+        vertices.extend(polyline(&[(5., 5.), (6., 5.)]));
+        // three lines meeting at (10, 0)
+        for end in [(11., 0.), (10., 1.), (9., 0.)] {
+            vertices.extend(polyline(&[(10., 0.), end]));
+        }
+        let mut expected = vec![None; vertices.len()];
+        expected[1] = Some(2);
+        expected[2] = Some(1);
+        assert_eq!(partners(&vertices), expected);
+    }
+
+    #[test]
+    fn right_angle_has_no_gap() {
+        let vertices = polyline(&[(0., 0.), (2., 0.), (2., 2.)]);
+        // outer corner of the joint, inner side is covered by the rectangles
+        let corner = || grid((2., -0.5), (2.5, 0.));
+
+        let miter = stroke(Join::Miter, Cap::Butt).triangles(&vertices);
+        assert!(corner().all(|point| covers(&miter, point)));
+
+        let bevel = stroke(Join::Bevel, Cap::Butt).triangles(&vertices);
+        for point in corner() {
+            assert_eq!(covers(&bevel, point), point.0 - point.1 <= 2.5 + 1e-12, "{point:?}");
+        }
+
+        let round = stroke(Join::Round, Cap::Butt).triangles(&vertices);
+        for point in corner() {
+            let distance = (point.0 - 2.).hypot(point.1);
+            if distance < 0.49 {
+                assert!(covers(&round, point), "{point:?}");
+            } else if distance > 0.5 {
+                assert!(!covers(&round, point), "{point:?}");
+            }
+        }
+
+        // only the outer ends of the polyline are capped
+        assert!(grid((0., -0.5), (2., 0.5)).chain(grid((1.5, 0.), (2.5, 2.))).all(|point| covers(&miter, point)));
+        assert!(!covers(&miter, (-0.1, 0.)) && !covers(&miter, (2., 2.1)));
+    }
+
+    #[test]
+    fn miter_falls_back_to_bevel_beyond_limit() {
+        // about 19° between the lines, so the miter is about 6 times the width
+        let vertices = polyline(&[(0., 0.), (2., 0.), (0., 0.7)]);
+        let miter = |miter_limit| Stroke { miter_limit, ..stroke(Join::Miter, Cap::Butt) }.triangles(&vertices);
+        let bevel = stroke(Join::Bevel, Cap::Butt).triangles(&vertices);
+        assert_eq!(miter(4.), bevel);
+        assert_ne!(miter(10.), bevel);
+        // tip of the miter
+        assert!(covers(&miter(10.), (4.5, -0.5)) && !covers(&bevel, (4.5, -0.5)));
+    }
+
+    #[test]
+    fn isolated_line_is_capped_at_both_ends() {
+        let vertices = polyline(&[(0., 0.), (2., 0.)]);
+        let count = |cap| stroke(Join::Miter, cap).triangles(&vertices).len() / 3;
+        assert_eq!([Cap::Butt, Cap::Square, Cap::Round].map(count), [2, 2 + 2 * 2, 2 + 2 * ROUND_SEGMENTS]);
+
+        let butt = stroke(Join::Miter, Cap::Butt).triangles(&vertices);
+        assert!(grid((0., -0.5), (2., 0.5)).all(|point| covers(&butt, point)));
+        assert!(!covers(&butt, (-0.1, 0.)) && !covers(&butt, (2.1, 0.)));
+
+        let square = stroke(Join::Miter, Cap::Square).triangles(&vertices);
+        assert!(grid((-0.5, -0.5), (2.5, 0.5)).all(|point| covers(&square, point)));
+        assert!(!covers(&square, (-0.6, 0.)) && !covers(&square, (2.6, 0.)));
+
+        let round = stroke(Join::Miter, Cap::Round).triangles(&vertices);
+        for x in [-0.45, 2.45] {
+            assert!(covers(&round, (x, 0.)));
+            assert!(!covers(&round, (x, 0.45)) && !covers(&round, (x, -0.45)));
+        }
+    }
+}
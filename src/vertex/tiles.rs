@@ -0,0 +1,279 @@
+//! spatial partitioning of indexed triangles into tiles (quadtree),
+//! so that only the tiles visible in the window have to be drawn
+
+use rayon::prelude::*;
+use std::ops::Range;
+
+use super::{Vertex, vec2::DVec2};
+
+/// tiles with at most this many triangles are not split further
+const MAX_TILE_TRIANGLES: usize = 4096;
+/// tiles are not split further below this depth
+const MAX_DEPTH: usize = 16;
+
+/// axis-aligned rectangle
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bounds {
+    pub min: DVec2,
+    pub max: DVec2,
+}
+
+impl Bounds {
+    /// contains nothing, grows when including positions
+    pub const EMPTY: Self = Self {
+        min: DVec2::new(f64::INFINITY, f64::INFINITY),
+        max: DVec2::new(f64::NEG_INFINITY, f64::NEG_INFINITY),
+    };
+
+    pub const fn new(min: DVec2, max: DVec2) -> Self {
+        Self { min, max }
+    }
+
+    /// smallest bounds containing both
+    pub const fn union(self, other: Self) -> Self {
+        Self::new(
+            DVec2::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y)),
+            DVec2::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y)),
+        )
+    }
+
+    /// smallest bounds containing self and `position`
+    pub const fn including(self, position: DVec2) -> Self {
+        self.union(Self::new(position, position))
+    }
+
+    /// `true` if both share at least one position (also when only touching)
+    pub const fn intersects(&self, other: &Self) -> bool {
+        self.min.x <= other.max.x && other.min.x <= self.max.x
+            && self.min.y <= other.max.y && other.min.y <= self.max.y
+    }
+
+    /// `true` if `other` lies completely inside of self
+    pub const fn contains(&self, other: &Self) -> bool {
+        self.min.x <= other.min.x && other.max.x <= self.max.x
+            && self.min.y <= other.min.y && other.max.y <= self.max.y
+    }
+}
+
+/// node of a [`Quadtree`]
+#[derive(Clone, Debug)]
+struct Node {
+    /// of all triangles of this node and its children
+    bounds: Bounds,
+    /// of all triangles of this node and its children in the indices
+    indices: Range<u32>,
+    /// indices of up to four nodes, empty for tiles
+    children: Vec<usize>,
+}
+
+/// triangles partitioned into tiles by the position of their centers.
+/// tiles cover disjoint ranges of the (reordered) indices,
+/// and are bounded by their triangles, so they may overlap
+#[derive(Clone, Debug)]
+pub struct Quadtree {
+    /// root first (if there are any triangles)
+    nodes: Vec<Node>,
+}
+
+impl Quadtree {
+    /// reorder `indices` (of triangles of `vertices`, as returned by [`super::index`])
+    /// so that the triangles of each tile are next to each other
+    pub fn new(vertices: &[Vertex], indices: &mut [u32]) -> Self {
+        log::debug!("partitioning triangles into tiles");
+        let triangles: &mut [[u32; 3]] = bytemuck::cast_slice_mut(indices);
+        let mut tree = Self { nodes: Vec::new() };
+        if triangles.is_empty() {
+            return tree;
+        }
+
+        let center = |triangle: &[u32; 3]| triangle.iter()
+            .fold(DVec2::default(), |sum, &i| sum + vertices[i as usize].position) / 3.;
+        let centers = triangles.par_iter()
+            .map(|triangle| Bounds::new(center(triangle), center(triangle)))
+            .reduce(|| Bounds::EMPTY, Bounds::union);
+
+        // in this order, the triangles of every quadrant at every depth are next to each other
+        let mut ordered = triangles.par_iter()
+            .map(|triangle| (z_order(&centers, center(triangle)), *triangle))
+            .collect::<Vec<_>>();
+        ordered.par_sort_unstable_by_key(|&(code, _)| code);
+        let codes = ordered.par_iter().map(|&(code, _)| code).collect::<Vec<_>>();
+        triangles.par_iter_mut().zip(ordered).for_each(|(triangle, (_, ordered))| *triangle = ordered);
+
+        tree.split(vertices, triangles, &codes, 0, 0);
+        log::info!("partitioned triangles into {} tiles", tree.tiles().count());
+        tree
+    }
+
+    /// add node for `triangles` (starting at `first` of all triangles)
+    /// in the same quadrant at `depth`, returns its index
+    fn split(&mut self, vertices: &[Vertex], triangles: &[[u32; 3]], codes: &[u32], first: usize, mut depth: usize) -> usize {
+        let node = self.nodes.len();
+        let indices = to_index(first)..to_index(first + triangles.len());
+        self.nodes.push(Node { bounds: Bounds::EMPTY, indices, children: Vec::new() });
+
+        let quadrant = |code: u32, depth: usize| (code >> (2 * (MAX_DEPTH - depth - 1))) & 0b11;
+        // skip depths at which all triangles are in the same quadrant
+        while depth < MAX_DEPTH && quadrant(codes[0], depth) == quadrant(codes[codes.len() - 1], depth) {
+            depth += 1;
+        }
+
+        if triangles.len() > MAX_TILE_TRIANGLES && depth < MAX_DEPTH {
+            let mut start = 0;
+            while start < triangles.len() {
+                let q = quadrant(codes[start], depth);
+                let end = start + codes[start..].partition_point(|&code| quadrant(code, depth) == q);
+                let child = self.split(vertices, &triangles[start..end], &codes[start..end], first + start, depth + 1);
+                self.nodes[node].children.push(child);
+                start = end;
+            }
+        }
+
+        self.nodes[node].bounds = if self.nodes[node].children.is_empty() {
+            triangles.par_iter()
+                .map(|triangle| triangle.iter().fold(Bounds::EMPTY, |bounds, &i| bounds.including(vertices[i as usize].position)))
+                .reduce(|| Bounds::EMPTY, Bounds::union)
+        } else {
+            self.nodes[node].children.iter()
+                .fold(Bounds::EMPTY, |bounds, &child| bounds.union(self.nodes[child].bounds))
+        };
+        node
+    }
+
+    /// bounds and ranges of indices of all tiles (nodes without children)
+    pub fn tiles(&self) -> impl Iterator<Item = (Bounds, Range<u32>)> + '_ {
+        self.nodes.iter()
+            .filter(|node| node.children.is_empty())
+            .map(|node| (node.bounds, node.indices.clone()))
+    }
+
+    /// ranges of indices of all tiles intersecting `viewport`,
+    /// ordered and with adjacent ranges merged to draw them at once
+    pub fn visible(&self, viewport: &Bounds) -> Vec<Range<u32>> {
+        let mut ranges: Vec<Range<u32>> = Vec::new();
+        if !self.nodes.is_empty() {
+            self.collect_visible(0, viewport, &mut ranges);
+        }
+        ranges
+    }
+
+    fn collect_visible(&self, node: usize, viewport: &Bounds, ranges: &mut Vec<Range<u32>>) {
+        let node = &self.nodes[node];
+        if !viewport.intersects(&node.bounds) {
+            return;
+        }
+        // all tiles below are visible as well
+        if node.children.is_empty() || viewport.contains(&node.bounds) {
+            match ranges.last_mut() {
+                Some(last) if last.end == node.indices.start => last.end = node.indices.end,
+                _ => ranges.push(node.indices.clone()),
+            }
+            return;
+        }
+        for &child in &node.children {
+            self.collect_visible(child, viewport, ranges);
+        }
+    }
+}
+
+/// position in `bounds` on a grid of 2^[`MAX_DEPTH`] cells per axis,
+/// with the bits of both axes interleaved (z-order curve),
+/// so that the two highest bits are the quadrant at depth 0
+fn z_order(bounds: &Bounds, position: DVec2) -> u32 {
+    #[allow(clippy::cast_precision_loss)]
+    let cells = (1_u32 << MAX_DEPTH) as f64;
+    let size = (bounds.max - bounds.min).map(|x| x.max(f64::MIN_POSITIVE));
+    let cell = ((position - bounds.min) / size * cells).map(|x| x.clamp(0., cells - 1.));
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let (x, y) = (cell.x as u32, cell.y as u32);
+    (0..MAX_DEPTH).fold(0, |code, bit| code | ((x >> bit) & 1) << (2 * bit) | ((y >> bit) & 1) << (2 * bit + 1))
+}
+
+/// index of a triangle as index of its first vertex in the indices
+fn to_index(triangle: usize) -> u32 {
+    (3 * triangle).try_into().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// two triangles for each cell of a `size`x`size` grid in [-1, 1]
+    fn grid(size: usize) -> (Vec<Vertex>, Vec<u32>) {
+        #[allow(clippy::cast_precision_loss)]
+        let corner = |x: usize, y: usize| DVec2::new(x as f64, y as f64) * (2. / size as f64) - DVec2::new(1., 1.);
+        let vertices = (0..size).flat_map(|y| (0..size).map(move |x| (x, y)))
+            .flat_map(|(x, y)| [
+                corner(x, y), corner(x + 1, y), corner(x + 1, y + 1),
+                corner(x, y), corner(x + 1, y + 1), corner(x, y + 1),
+            ])
+            .map(|position| Vertex::new(position, 0))
+            .collect::<Vec<_>>();
+        let indices = (0..vertices.len().try_into().unwrap()).collect();
+        (vertices, indices)
+    }
+
+    /// ranges of all tiles intersecting `viewport`, merged like [`Quadtree::visible`]
+    fn intersecting(tree: &Quadtree, viewport: &Bounds) -> Vec<Range<u32>> {
+        let mut tiles = tree.tiles()
+            .filter(|(bounds, _)| viewport.intersects(bounds))
+            .map(|(_, range)| range)
+            .collect::<Vec<_>>();
+        tiles.sort_by_key(|range| range.start);
+
+        let mut ranges: Vec<Range<u32>> = Vec::new();
+        for range in tiles {
+            match ranges.last_mut() {
+                Some(last) if last.end == range.start => last.end = range.end,
+                _ => ranges.push(range),
+            }
+        }
+        ranges
+    }
+
+    #[test]
+    fn tiles_partition_triangles() {
+        let (vertices, mut indices) = grid(100);
+        let tree = Quadtree::new(&vertices, &mut indices);
+
+        let mut ranges = tree.tiles().map(|(_, range)| range).collect::<Vec<_>>();
+        assert!(ranges.len() > 1);
+        ranges.sort_by_key(|range| range.start);
+        assert_eq!(ranges[0].start, 0);
+        assert_eq!(ranges[ranges.len() - 1].end, indices.len().try_into().unwrap());
+        assert!(ranges.windows(2).all(|pair| pair[0].end == pair[1].start));
+
+        // every triangle is inside the bounds of its tile
+        for (bounds, range) in tree.tiles() {
+            for &i in &indices[range.start as usize..range.end as usize] {
+                let position = vertices[i as usize].position;
+                assert!(bounds.contains(&Bounds::new(position, position)));
+            }
+        }
+    }
+
+    #[test]
+    fn visible_selects_exactly_intersecting_tiles() {
+        let (vertices, mut indices) = grid(100);
+        let tree = Quadtree::new(&vertices, &mut indices);
+
+        let viewports = [
+            Bounds::new(DVec2::new(-0.1, -0.1), DVec2::new(0.1, 0.1)),
+            Bounds::new(DVec2::new(-1., 0.3), DVec2::new(-0.6, 0.9)),
+            Bounds::new(DVec2::new(0.55, -0.95), DVec2::new(0.56, -0.94)),
+            Bounds::new(DVec2::new(-0.5, -2.), DVec2::new(2., 0.25)),
+        ];
+        for viewport in &viewports {
+            let visible = tree.visible(viewport);
+            assert!(!visible.is_empty());
+            assert_eq!(visible, intersecting(&tree, viewport), "{viewport:?}");
+        }
+
+        let outside = Bounds::new(DVec2::new(1.5, 1.5), DVec2::new(2., 2.));
+        assert!(tree.visible(&outside).is_empty());
+
+        let everything = Bounds::new(DVec2::new(-2., -2.), DVec2::new(2., 2.));
+        assert_eq!(tree.visible(&everything), vec![0..indices.len().try_into().unwrap()]);
+    }
+}
@@ -0,0 +1,160 @@
+use std::ops;
+
+#[repr(C)]
+#[derive(
+    Clone, Copy, PartialEq, Debug, Default,
+    // allow bitwise casts with bytemuck
+    bytemuck::Zeroable, bytemuck::Pod,
+)]
+/// 2D vector with `x` and `y` in \[-1, 1\], for buffers and rendering
+pub struct Vec2 {
+    pub x: f32,
+    pub y: f32,
+}
+
+#[repr(C)]
+#[derive(
+    Clone, Copy, PartialEq, Debug, Default,
+    // allow bitwise casts with bytemuck
+    bytemuck::Zeroable, bytemuck::Pod,
+)]
+/// [`Vec2`] with double precision, for computing fractal geometry
+/// that stays precise when zooming in far
+pub struct DVec2 {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl DVec2 {
+    /// with the precision of buffers
+    #[allow(clippy::cast_possible_truncation)]
+    pub const fn to_f32(self) -> Vec2 {
+        Vec2::new(self.x as f32, self.y as f32)
+    }
+}
+
+impl From<Vec2> for DVec2 {
+    fn from(value: Vec2) -> Self {
+        Self::new(value.x.into(), value.y.into())
+    }
+}
+
+/// same methods and operators for both precisions
+macro_rules! impl_vec2 {
+    ($vec:ident, $float:ty) => {
+        impl $vec {
+            pub const fn new(x: $float, y: $float) -> Self {
+                Self { x, y }
+            }
+
+            /// apply `f` to both fields
+            pub fn map<F: Fn($float) -> $float>(self, f: F) -> Self {
+                Self::new(f(self.x), f(self.y))
+            }
+
+            pub fn len(self) -> $float {
+                self.x.hypot(self.y)
+            }
+
+            pub fn set_len(self, len: $float) -> Self {
+                let current_len= self.len();
+
+                #[allow(clippy::float_cmp)]
+                if current_len == len {
+                    return self;
+                }
+
+                let normalized = self.map(|x| x / current_len);
+
+                normalized * len
+            }
+
+            /// clockwise/+90° orthogonal vector of self with same length
+            pub const fn clockwise_orthogonal(self) -> Self {
+                Self::new(self.y, -self.x)
+            }
+
+            /// clockwise, angle in radians
+            pub fn rotate_cw(self, angle: $float) -> Self {
+                Self::new(
+                      self.x .mul_add(angle.cos(), self.y * angle.sin()),
+                    (-self.x).mul_add(angle.sin(), self.y * angle.cos()),
+                )
+            }
+
+            /// counterclockwise, angle in radians
+            pub fn rotate_ccw(self, angle: $float) -> Self {
+                Self::new(
+                    self.x.mul_add(angle.cos(), -(self.y * angle.sin())),
+                    self.x.mul_add(angle.sin(),   self.y * angle.cos() ),
+                )
+            }
+        }
+
+        impl ops::Add for $vec {
+            type Output = Self;
+            fn add(self, rhs: Self) -> Self::Output {
+                Self::new(self.x + rhs.x, self.y + rhs.y)
+            }
+        }
+
+        impl ops::Add<$float> for $vec {
+            type Output = Self;
+            fn add(self, rhs: $float) -> Self::Output {
+                self.map(|x| x + rhs)
+            }
+        }
+
+        impl ops::Sub for $vec {
+            type Output = Self;
+            fn sub(self, rhs: Self) -> Self::Output {
+                Self::new(self.x - rhs.x, self.y - rhs.y)
+            }
+        }
+
+        impl ops::Sub<$float> for $vec {
+            type Output = Self;
+            fn sub(self, rhs: $float) -> Self::Output {
+                self.map(|x| x - rhs)
+            }
+        }
+
+        impl ops::Mul for $vec {
+            type Output = Self;
+            fn mul(self, rhs: Self) -> Self::Output {
+                Self::new(self.x * rhs.x, self.y * rhs.y)
+            }
+        }
+
+        impl ops::Mul<$float> for $vec {
+            type Output = Self;
+            fn mul(self, rhs: $float) -> Self::Output {
+                self.map(|x| x * rhs)
+            }
+        }
+
+        impl ops::Div for $vec {
+            type Output = Self;
+            fn div(self, rhs: Self) -> Self::Output {
+                Self::new(self.x / rhs.x, self.y / rhs.y)
+            }
+        }
+
+        impl ops::Div<$float> for $vec {
+            type Output = Self;
+            fn div(self, rhs: $float) -> Self::Output {
+                self.map(|x| x / rhs)
+            }
+        }
+
+        impl ops::Neg for $vec {
+            type Output = Self;
+            fn neg(self) -> Self::Output {
+                self.map(|x| -x)
+            }
+        }
+    };
+}
+
+impl_vec2!(Vec2, f32);
+impl_vec2!(DVec2, f64);